@@ -1,4 +1,4 @@
-import init, { type TypstRequest, TypstState } from "mnemo-wasm";
+import init, { type TypstFontVariant, type TypstRequest, TypstState } from "mnemo-wasm";
 
 export function getTypstFontImports() {
   return [
@@ -22,6 +22,26 @@ export function getTypstFontImports() {
   ];
 }
 
+/** A face as described by `fontIndex`, whose stretch is in thousandths of the normal width. */
+interface TypstFontFace {
+  family: string;
+  variant: { style: string; weight: number; stretch: number };
+}
+
+const fontIndexKey = (fileUrl: string) => `typst-font-index:${fileUrl}`;
+const fontFaceKey = (family: string, { style, weight, stretch }: TypstFontVariant) =>
+  `${family.toLowerCase()}|${style}|${weight}|${stretch}`;
+
+/** Font files registered from a cached index but not loaded yet, by face. */
+const lazyFontUrls = new Map<string, string>();
+
+async function fetchFont(fileUrl: string) {
+  const response = await fetch(fileUrl);
+  const buffer = await response.arrayBuffer();
+
+  return new Uint8Array(buffer);
+}
+
 export const useTypst = createSharedComposable(async () =>
   init().then(async () => {
     const typstState = new TypstState();
@@ -34,10 +54,24 @@ export const useTypst = createSharedComposable(async () =>
         fontImports.map(async (fontImport) => {
           const { default: fileUrl } = await fontImport;
 
-          const response = await fetch(fileUrl);
-          const buffer = await response.arrayBuffer();
-          const bytes = new Uint8Array(buffer);
+          // Files seen before are only registered, and loaded once a note uses them.
+          const cachedIndex = localStorage.getItem(fontIndexKey(fileUrl));
+          if (cachedIndex !== null) {
+            // oxlint-disable-next-line typescript/no-unsafe-type-assertion
+            const faces = JSON.parse(cachedIndex) as TypstFontFace[];
+            typstState.registerFonts(faces, undefined);
+
+            for (const { family, variant } of faces) {
+              const stretch = variant.stretch / 1000;
+              lazyFontUrls.set(fontFaceKey(family, { ...variant, stretch }), fileUrl);
+            }
 
+            return;
+          }
+
+          const bytes = await fetchFont(fileUrl);
+
+          localStorage.setItem(fontIndexKey(fileUrl), JSON.stringify(TypstState.fontIndex(bytes)));
           typstState.installFont(bytes);
         }),
       );
@@ -62,7 +96,7 @@ export const installTypstPackage = useMemoize((pkg: TypstPackageSpec, spaceId: s
   const spec = usePackageSpec(pkg);
 
   // oxlint-disable-next-line no-async-promise-executor typescript/no-misused-promises
-  return new Promise<void>(async (resolve, reject) => {
+  return new Promise<TypstRequest[]>(async (resolve, reject) => {
     const spaces = await useSpaces();
     const space = spaces.value[spaceId]!;
 
@@ -74,9 +108,8 @@ export const installTypstPackage = useMemoize((pkg: TypstPackageSpec, spaceId: s
     );
 
     if (hasPackage) {
-      await loadTypstPackage(pkg).catch(reject);
+      await loadTypstPackage(pkg).then(resolve, reject);
 
-      resolve();
       return;
     }
 
@@ -94,17 +127,17 @@ export const installTypstPackage = useMemoize((pkg: TypstPackageSpec, spaceId: s
             variant: "primary",
             async onClick() {
               try {
-                await loadTypstPackage(pkg);
+                const dependencies = await loadTypstPackage(pkg);
 
                 const installedPackages = await useInstalledPackages(spaceId);
                 void installedPackages.push(pkg);
 
-                resolve();
+                resolve(dependencies);
               } catch (error) {
                 createNotification(
                   t("composables.typst.error-installing", {
                     package: spec,
-                    error: error instanceof Error ? error.message : String(error),
+                    error: typstErrorMessage(error),
                   }),
                   { type: "error" },
                 );
@@ -139,9 +172,29 @@ export async function loadTypstPackage(pkg: TypstPackageSpec) {
   const buffer = await blob.arrayBuffer();
 
   const typstState = await useTypst();
-  typstState.installPackage(spec, new Uint8Array(buffer));
+  const dependencies = typstState.installPackage(spec, new Uint8Array(buffer));
 
   dismiss(notifId);
+
+  return dependencies;
+}
+
+/** Removes a package from the typst state, so it is downloaded again when next used. */
+export async function unloadTypstPackage(pkg: TypstPackageSpec) {
+  const typstState = await useTypst();
+  typstState.uninstallPackage(usePackageSpec(pkg));
+
+  installTypstPackage.clear();
+}
+
+/** Reads the message of an `Error` or a `TypstError` thrown by the typst state. */
+export function typstErrorMessage(error: unknown) {
+  if (error instanceof Error) return error.message;
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return String(error.message);
+  }
+
+  return String(error);
 }
 
 export function usePackageSpec(pkg: TypstPackageSpec) {
@@ -203,7 +256,29 @@ const handleTypstRequest = useMemoize(async (request: TypstRequest, spaceId: str
 
     case "package": {
       const pkg = request.value;
-      await installTypstPackage(pkg, spaceId);
+      // Local packages come from space files registered with `registerLocalPackage`.
+      if (pkg.namespace === "local") return false;
+
+      const dependencies = await installTypstPackage(pkg, spaceId);
+
+      // Every missing dependency is fetched now, rather than one compile at a time.
+      await handleTypstRequests(dependencies, spaceId);
+
+      return true;
+    }
+
+    case "font": {
+      const { family, variant } = request.value;
+
+      const fileUrl = lazyFontUrls.get(fontFaceKey(family, variant));
+      if (fileUrl === undefined) return false;
+
+      for (const [key, url] of lazyFontUrls) {
+        if (url === fileUrl) lazyFontUrls.delete(key);
+      }
+
+      const typstState = await useTypst();
+      typstState.installFont(await fetchFont(fileUrl));
 
       return true;
     }
@@ -69,8 +69,13 @@ class TypstWidget extends WidgetType {
     const x = clientX - left;
     const y = clientY - top;
 
-    const jump = typstState.jumpPaged(this.fileId, x, y + frame.render.yOffset);
-    const position = jump ? jump.position : frame.range.end;
+    const jump = typstState.jumpPaged(
+      this.fileId,
+      frame.render.page,
+      x,
+      y + frame.render.yOffset,
+    );
+    const position = jump?.type === "File" ? jump.position : frame.range.end;
 
     view.focus();
     view.dispatch({ selection: { anchor: position } });
@@ -319,33 +324,39 @@ function dispatchDiagnostics(
   view: EditorView,
 ) {
   if (typstDiagnostics.length > 0) {
-    const diagnostics = typstDiagnostics.map((diagnostic) => ({
-      from: diagnostic.range.start,
-      to: diagnostic.range.end,
-      severity: diagnostic.severity,
-      message: diagnostic.message,
-      renderMessage() {
-        const frag = document.createDocumentFragment();
-        const p = document.createElement("p");
-        parseBackticks(diagnostic.message, p);
-        frag.append(p);
-
-        if (diagnostic.hints.length) {
-          const ul = document.createElement("ul");
-          ul.className = "typst-hints";
-
-          for (const hint of diagnostic.hints) {
-            const li = document.createElement("li");
-            parseBackticks(hint, li);
-            ul.append(li);
+    const diagnostics = typstDiagnostics.map((diagnostic) => {
+      // Diagnostics in other files, like the space library, are shown at the start of the note.
+      const path = diagnostic.path ?? undefined;
+      const message = path === undefined ? diagnostic.message : `${path}: ${diagnostic.message}`;
+
+      return {
+        from: path === undefined ? diagnostic.range.start : 0,
+        to: path === undefined ? diagnostic.range.end : 0,
+        severity: diagnostic.severity,
+        message,
+        renderMessage() {
+          const frag = document.createDocumentFragment();
+          const p = document.createElement("p");
+          parseBackticks(message, p);
+          frag.append(p);
+
+          if (diagnostic.hints.length) {
+            const ul = document.createElement("ul");
+            ul.className = "typst-hints";
+
+            for (const hint of diagnostic.hints) {
+              const li = document.createElement("li");
+              parseBackticks(hint, li);
+              ul.append(li);
+            }
+
+            frag.append(ul);
           }
 
-          frag.append(ul);
-        }
-
-        return frag;
-      },
-    }));
+          return frag;
+        },
+      };
+    });
 
     const transaction = setDiagnostics(state, diagnostics);
     view.dispatch(transaction);
@@ -80,5 +80,5 @@ fn resolve_span(world: &MnemoWorld, span: Span) -> Option<(String, u32)> {
     let range = world.range(span)?;
     let line = source.lines().byte_to_line(range.start)?;
 
-    Some((format!("{id:?}"), line as u32 + 1))
+    Some((format!("{id:?}"), u32::try_from(line + 1).ok()?))
 }
@@ -1,11 +1,15 @@
-use std::fs;
+use std::{fs, path::PathBuf};
 
 use criterion::{Criterion, criterion_group, criterion_main};
-use mnemo_wasm::{renderer::paged::svg::render_svgs_by_items, state::TypstState};
+use mnemo_wasm::{
+    renderer::paged::svg::render_svgs_by_items,
+    source::{RenderTarget, sync_source_state},
+    state::TypstState,
+};
 
 fn benchmark_renderers(c: &mut Criterion) {
     let filename = String::from("math.typ");
-    let text = fs::read_to_string(&filename).unwrap();
+    let text = fs::read_to_string(PathBuf::from("benches").join(&filename)).unwrap();
 
     let mut state = TypstState::default();
 
@@ -16,8 +20,7 @@ fn benchmark_renderers(c: &mut Criterion) {
         include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
     );
 
-    let id = state.create_file_id(&filename);
-    state.insert_source(&id, text.clone());
+    let id = state.create_source_id(&filename, String::from("bench"));
 
     let prelude = "";
 
@@ -30,5 +33,43 @@ fn benchmark_renderers(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, benchmark_renderers);
+fn benchmark_synth(c: &mut Criterion) {
+    for filename in ["math.typ", "test.typ"] {
+        let text = fs::read_to_string(PathBuf::from("benches").join(filename)).unwrap();
+
+        // Insert a word in the middle of the document, at a line boundary.
+        let middle = text[..text.len() / 2].rfind('\n').map_or(0, |idx| idx + 1);
+        let edited = format!("{}edited {}", &text[..middle], &text[middle..]);
+
+        let mut state = TypstState::default();
+        let id = state.create_source_id(filename, String::from("bench"));
+
+        let prelude = "";
+
+        c.bench_function(&format!("sync {filename} (cold)"), |b| {
+            let mut edit = false;
+
+            b.iter(|| {
+                edit = !edit;
+                state.get_source_context_mut(&id).synth_cache = None;
+
+                let text = if edit { &edited } else { &text };
+                sync_source_state(&id, text, prelude, RenderTarget::Svg, &mut state)
+            });
+        });
+
+        c.bench_function(&format!("sync {filename} (incremental)"), |b| {
+            let mut edit = false;
+
+            b.iter(|| {
+                edit = !edit;
+
+                let text = if edit { &edited } else { &text };
+                sync_source_state(&id, text, prelude, RenderTarget::Svg, &mut state)
+            });
+        });
+    }
+}
+
+criterion_group!(benches, benchmark_renderers, benchmark_synth);
 criterion_main!(benches);
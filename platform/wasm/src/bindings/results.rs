@@ -2,8 +2,12 @@ use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 
 use crate::{
-    bindings::TypstDiagnostic,
-    renderer::{html::HTMLRangedFrame, paged::svg::SvgRangedFrame},
+    bindings::{TypstDiagnostic, TypstRecovered},
+    renderer::{
+        export::ExportDiagnostic,
+        html::HTMLRangedFrame,
+        paged::svg::{SvgPage, SvgRangedFrame},
+    },
     state::TypstRequest,
 };
 
@@ -12,7 +16,10 @@ use crate::{
 pub struct CompilePagedResult {
     pub frames: Vec<SvgRangedFrame>,
     pub tooltips: Vec<SvgRangedFrame>,
+    pub pages: Vec<SvgPage>,
     pub diagnostics: Vec<TypstDiagnostic>,
+    /// Parts of the note that recovery neutralized to render the rest.
+    pub recovered: Vec<TypstRecovered>,
     pub requests: Vec<TypstRequest>,
 }
 
@@ -21,6 +28,8 @@ pub struct CompilePagedResult {
 pub struct CompileHTMLResult {
     pub frames: Vec<HTMLRangedFrame>,
     pub diagnostics: Vec<TypstDiagnostic>,
+    /// Parts of the note that recovery neutralized to render the rest.
+    pub recovered: Vec<TypstRecovered>,
     pub requests: Vec<TypstRequest>,
 }
 
@@ -30,3 +39,31 @@ pub struct CheckResult {
     pub diagnostics: Vec<TypstDiagnostic>,
     pub requests: Vec<TypstRequest>,
 }
+
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RenderPdfResult {
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array | undefined")]
+    pub bytes: Option<Vec<u8>>,
+    pub diagnostics: Vec<TypstDiagnostic>,
+    pub requests: Vec<TypstRequest>,
+}
+
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ExportPdfResult {
+    #[serde(with = "serde_bytes")]
+    #[tsify(type = "Uint8Array | undefined")]
+    pub bytes: Option<Vec<u8>>,
+    pub diagnostics: Vec<ExportDiagnostic>,
+    pub requests: Vec<TypstRequest>,
+}
+
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ExportHtmlResult {
+    pub html: Option<String>,
+    pub diagnostics: Vec<ExportDiagnostic>,
+    pub requests: Vec<TypstRequest>,
+}
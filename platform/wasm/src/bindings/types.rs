@@ -1,4 +1,4 @@
-use std::ops::Range;
+use std::{collections::BTreeMap, ops::Range};
 
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
@@ -6,11 +6,17 @@ use typst::{
     World, WorldExt,
     diag::{Severity, SourceDiagnostic, Tracepoint},
     ecow::{EcoVec, eco_format},
-    syntax::{DiagSpan, FileId, Spanned},
+    foundations::{Dict, Str, Value},
+    syntax::{
+        DiagSpan, FileId, Span, Spanned, VirtualRoot,
+        package::{PackageManifest, PackageSpec},
+    },
+    text::{FontStyle, FontVariant},
 };
+use typst_ide::Definition;
 use wasm_bindgen::prelude::*;
 
-use crate::{source::SourceContext, world::MnemoWorld};
+use crate::{renderer::paged::FrameBounds, source::SourceContext, world::MnemoWorld};
 
 #[wasm_bindgen(js_name = "FileId")]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -31,7 +37,11 @@ impl TypstFileId {
 #[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct TypstDiagnostic {
+    /// UTF-16 range in the raw source, or in the file at `path`.
     pub range: Range<usize>,
+    /// Path of the file the diagnostic is in, when it is not the note but a
+    /// file imported by the prelude, like the space library.
+    pub path: Option<String>,
     pub severity: TypstDiagnosticSeverity,
     pub message: String,
     pub hints: Box<[String]>,
@@ -56,30 +66,75 @@ impl TypstDiagnostic {
                     diagnostic.message = eco_format!("failed to load file: {text}");
                 }
 
-                map_raw_span(
-                    diagnostic.span,
-                    diagnostic.severity == Severity::Error,
-                    &diagnostic.trace,
-                    context,
-                    world,
-                )
-                .map(|range| {
-                    TypstDiagnostic {
-                        range,
-                        severity: TypstDiagnosticSeverity::from_severity(diagnostic.severity),
-                        message: diagnostic.message.to_string(),
-                        hints: diagnostic
-                            .hints
-                            .into_iter()
-                            .map(|s| s.v.to_string())
-                            .collect(),
-                    }
+                let (path, range) = if let Some((path, range)) =
+                    map_prelude_import_span(&diagnostic, context, world)
+                {
+                    (Some(path), range)
+                } else {
+                    let range = map_raw_span(
+                        diagnostic.span,
+                        diagnostic.severity == Severity::Error,
+                        &diagnostic.trace,
+                        context,
+                        world,
+                    )?;
+
+                    (None, range)
+                };
+
+                Some(TypstDiagnostic {
+                    range,
+                    path,
+                    severity: TypstDiagnosticSeverity::from_severity(diagnostic.severity),
+                    message: diagnostic.message.to_string(),
+                    hints: diagnostic
+                        .hints
+                        .into_iter()
+                        .map(|s| s.v.to_string())
+                        .collect(),
                 })
             })
             .collect()
     }
 }
 
+/// Maps a diagnostic in a file imported by the prelude, rather than by the
+/// note, to its path and UTF-16 range in that file.
+fn map_prelude_import_span(
+    diagnostic: &SourceDiagnostic,
+    context: &SourceContext,
+    world: &MnemoWorld,
+) -> Option<(String, Range<usize>)> {
+    let id = diagnostic.span.id()?;
+    if id == context.synth_id || !matches!(id.root(), VirtualRoot::Project) {
+        return None;
+    }
+
+    let prelude_len = context
+        .synth_cache
+        .as_ref()
+        .map_or(0, |cache| cache.prelude.len());
+    let synth_range = map_synth_span(
+        diagnostic.span,
+        true,
+        &diagnostic.trace,
+        context,
+        world,
+    );
+    if synth_range.is_some_and(|synth_range| synth_range.start >= prelude_len) {
+        return None;
+    }
+
+    let source = world.source(id).ok()?;
+    let range = world.range(diagnostic.span)?;
+    let lines = source.lines();
+
+    Some((
+        id.vpath().get_with_slash().to_string(),
+        lines.byte_to_utf16(range.start)?..lines.byte_to_utf16(range.end)?,
+    ))
+}
+
 pub fn map_synth_span(
     span: impl Into<DiagSpan>,
     is_error: bool,
@@ -144,6 +199,18 @@ pub fn map_raw_span(
     Some(raw_range_utf16)
 }
 
+/// A part of a note that recovery neutralized so the rest of it could render.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstRecovered {
+    /// UTF-16 range in the raw source.
+    pub range: Range<usize>,
+    /// Name of the recovery strategy used, like `"block-blanking"`.
+    pub strategy: String,
+    /// The error that triggered recovery.
+    pub diagnostic: TypstDiagnostic,
+}
+
 #[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 #[serde(rename_all = "kebab-case")]
@@ -164,6 +231,15 @@ impl TypstDiagnosticSeverity {
     }
 }
 
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstTextEdit {
+    /// UTF-16 range to replace, in the text as left by the previous edit.
+    pub range: Range<usize>,
+    /// The replacement text.
+    pub text: String,
+}
+
 #[derive(Tsify, Serialize, Deserialize, Debug)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct TypstHighlight {
@@ -175,12 +251,17 @@ pub struct TypstHighlight {
 #[tsify(into_wasm_abi, from_wasm_abi)]
 #[serde(tag = "type")]
 pub enum TypstJump {
-    File {
-        // id: u64,
-        position: usize,
-    },
-    // Url(String),
-    // Position(Position),
+    /// A UTF-16 position in the note's raw source.
+    File { position: usize },
+    /// A UTF-16 position in another project file, such as an import.
+    #[serde(rename_all = "camelCase")]
+    OtherFile { path: String, position: usize },
+    /// An external link.
+    Url { url: String },
+    /// A point in the rendered note, such as the target of a `@ref`, relative
+    /// to the top-left corner of a frame.
+    #[serde(rename_all = "camelCase")]
+    Position { frame_index: usize, x: f64, y: f64 },
 }
 
 impl TypstJump {
@@ -190,8 +271,8 @@ impl TypstJump {
         world: &MnemoWorld,
     ) -> Option<Self> {
         match jump {
-            typst_ide::Jump::File(id, synth_position) => {
-                if id != context.synth_id {
+            typst_ide::Jump::File(id, synth_position) if id == context.synth_id => {
+                if context.in_prelude(synth_position) {
                     return None;
                 }
 
@@ -200,16 +281,256 @@ impl TypstJump {
                 let raw_position_utf16 = raw_source.lines().byte_to_utf16(raw_position)?;
 
                 Some(Self::File {
-                    // id: state.finish(),
                     position: raw_position_utf16,
                 })
             }
-            typst_ide::Jump::Url(..) => None,
-            typst_ide::Jump::Position(..) => None,
+            typst_ide::Jump::File(id, position) => {
+                let source = world.source(id).ok()?;
+
+                Some(Self::OtherFile {
+                    path: id.vpath().get_with_slash().to_string(),
+                    position: source.lines().byte_to_utf16(position)?,
+                })
+            }
+            typst_ide::Jump::Url(url) => {
+                Some(Self::Url {
+                    url: url.as_str().to_string(),
+                })
+            }
+            typst_ide::Jump::Position(position) => {
+                let (frame_index, x, y) = FrameBounds::locate(
+                    &context.frame_bounds,
+                    position.page.get() - 1,
+                    position.point,
+                )?;
+
+                Some(Self::Position { frame_index, x, y })
+            }
         }
     }
 }
 
+/// A font family available to a space.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstFont {
+    pub family: String,
+    /// Whether the family is installed for every space rather than registered
+    /// against this one.
+    pub shared: bool,
+}
+
+/// An installed package.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstPackage {
+    pub namespace: String,
+    pub name: String,
+    pub version: String,
+    /// The description from the package's `typst.toml`.
+    pub description: Option<String>,
+}
+
+impl TypstPackage {
+    #[must_use]
+    pub fn new(spec: &PackageSpec, manifest: &PackageManifest) -> Self {
+        Self {
+            namespace: spec.namespace.to_string(),
+            name: spec.name.to_string(),
+            version: spec.version.to_string(),
+            description: manifest
+                .package
+                .description
+                .as_ref()
+                .map(ToString::to_string),
+        }
+    }
+}
+
+/// An installed or registered font family, for the settings UI.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstFontFamily {
+    pub family: String,
+    pub variants: Vec<TypstFontVariant>,
+    /// Whether any face has an OpenType MATH table, as math fonts need.
+    pub math: bool,
+    /// Whether every face is monospaced.
+    pub monospace: bool,
+    /// Names of the scripts covered by any face, e.g. `latin` or `han`.
+    pub scripts: Vec<String>,
+}
+
+/// Characters of a note that its configured font families cannot display.
+#[derive(Tsify, Serialize, Deserialize, Debug, Default)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct TypstFontCoverage {
+    /// Configured families that are not installed.
+    pub missing_families: Vec<String>,
+    pub missing_glyphs: Vec<TypstMissingGlyph>,
+}
+
+/// A character with no glyph in the family configured for where it is used.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstMissingGlyph {
+    pub character: String,
+    /// The text, math or code family that lacks the glyph.
+    pub family: String,
+    /// How many times the character is used with that family.
+    pub count: usize,
+    /// UTF-16 range of the first use in the raw source.
+    pub range: Range<usize>,
+}
+
+/// The style, weight and stretch of a font face.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstFontVariant {
+    /// `normal`, `italic` or `oblique`.
+    pub style: String,
+    /// The weight, from 100 to 900.
+    pub weight: u16,
+    /// The width relative to normal, from 0.5 to 2.
+    pub stretch: f64,
+}
+
+impl From<FontVariant> for TypstFontVariant {
+    fn from(variant: FontVariant) -> Self {
+        let style = match variant.style {
+            FontStyle::Normal => "normal",
+            FontStyle::Italic => "italic",
+            FontStyle::Oblique => "oblique",
+        };
+
+        Self {
+            style: style.to_string(),
+            weight: variant.weight.to_number(),
+            stretch: variant.stretch.to_ratio().get(),
+        }
+    }
+}
+
+/// Where the editor cursor shows up in the rendered preview.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TypstCursorJump {
+    /// A point inside a rendered SVG frame, relative to its top-left corner.
+    #[serde(rename_all = "camelCase")]
+    Paged { frame_index: usize, x: f64, y: f64 },
+    /// The child indices leading to an element of the rendered HTML.
+    Html { element: Vec<usize> },
+}
+
+/// Where a symbol is defined or referenced.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TypstLocation {
+    /// A UTF-16 range in the note's raw source.
+    Raw { range: Range<usize> },
+    /// A definition in the generated prelude, such as `theme`.
+    Prelude { name: String },
+    /// A UTF-16 range in another project file.
+    File { path: String, range: Range<usize> },
+    /// An item of the standard library.
+    Std { name: String },
+}
+
+impl TypstLocation {
+    pub fn from_definition(
+        definition: Definition,
+        context: &SourceContext,
+        world: &MnemoWorld,
+    ) -> Option<Self> {
+        match definition {
+            Definition::Span(span) => Self::from_span(span, context, world),
+            Definition::File(id) => {
+                Some(Self::File {
+                    path: id.vpath().get_with_slash().to_string(),
+                    range: 0..0,
+                })
+            }
+            Definition::Std(value) => {
+                let name = match &value {
+                    Value::Func(func) => func.name()?.to_string(),
+                    Value::Type(ty) => ty.short_name().to_string(),
+                    Value::Module(module) => module.name()?.to_string(),
+                    _ => return None,
+                };
+
+                Some(Self::Std { name })
+            }
+        }
+    }
+
+    pub fn from_span(span: Span, context: &SourceContext, world: &MnemoWorld) -> Option<Self> {
+        let id = span.id()?;
+        let range = world.range(span)?;
+
+        if id != context.synth_id {
+            let source = world.source(id).ok()?;
+            let lines = source.lines();
+
+            return Some(Self::File {
+                path: id.vpath().get_with_slash().to_string(),
+                range: lines.byte_to_utf16(range.start)?..lines.byte_to_utf16(range.end)?,
+            });
+        }
+
+        if context.in_prelude(range.start) {
+            let synth_source = context.synth_source(world)?;
+
+            return Some(Self::Prelude {
+                name: synth_source.text().get(range)?.to_string(),
+            });
+        }
+
+        let raw_source = context.raw_source(world)?;
+        let raw_start = context.map_synth_to_raw_from_right(range.start);
+        let raw_end = context.map_synth_to_raw_from_left(range.end);
+
+        let raw_lines = raw_source.lines();
+        let raw_start_utf16 = raw_lines.byte_to_utf16(raw_start)?;
+        let raw_end_utf16 = raw_lines.byte_to_utf16(raw_end)?;
+
+        Some(Self::Raw {
+            range: raw_start_utf16..raw_end_utf16,
+        })
+    }
+}
+
+/// The signature of the function call around the cursor.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct TypstSignatureHelp {
+    /// The full signature, e.g. `figure(body: content, caption: none | content)`.
+    pub label: String,
+    /// The function's documentation, in Markdown.
+    pub docs: Option<String>,
+    pub params: Vec<TypstParameter>,
+    /// Index into `params` of the argument being written, if any matches.
+    pub active_param: Option<usize>,
+}
+
+/// A parameter in a [`TypstSignatureHelp`].
+#[derive(Tsify, Serialize, Deserialize, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TypstParameter {
+    /// The parameter as shown in the signature, e.g. `caption: none | content`.
+    pub label: String,
+    /// The parameter's documentation, in Markdown.
+    pub docs: String,
+    pub positional: bool,
+    pub named: bool,
+    pub required: bool,
+    pub variadic: bool,
+}
+
 #[derive(Tsify, Serialize, Deserialize, Debug)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 #[serde(rename_all = "kebab-case")]
@@ -230,10 +551,10 @@ pub enum TypstCompletionKind {
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct TypstCompletion {
     #[serde(rename = "type")]
-    kind: TypstCompletionKind,
-    label: String,
-    apply: Option<String>,
-    detail: Option<String>,
+    pub kind: TypstCompletionKind,
+    pub label: String,
+    pub apply: Option<String>,
+    pub detail: Option<String>,
 }
 
 impl From<typst_ide::Completion> for TypstCompletion {
@@ -257,3 +578,41 @@ impl From<typst_ide::Completion> for TypstCompletion {
         }
     }
 }
+
+/// A value of a note's `sys.inputs`, as passed from JavaScript.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(untagged)]
+pub enum TypstInput {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<TypstInput>),
+    Dict(BTreeMap<String, TypstInput>),
+}
+
+impl TypstInput {
+    /// Converts a dictionary of inputs into the one Typst exposes.
+    #[must_use]
+    pub fn into_dict(inputs: BTreeMap<String, Self>) -> Dict {
+        inputs
+            .into_iter()
+            .map(|(key, value)| (Str::from(key), value.into_value()))
+            .collect()
+    }
+
+    #[must_use]
+    pub fn into_value(self) -> Value {
+        match self {
+            Self::None => Value::None,
+            Self::Bool(value) => Value::Bool(value),
+            Self::Int(value) => Value::Int(value),
+            Self::Float(value) => Value::Float(value),
+            Self::Str(value) => Value::Str(value.into()),
+            Self::Array(values) => Value::Array(values.into_iter().map(Self::into_value).collect()),
+            Self::Dict(inputs) => Value::Dict(Self::into_dict(inputs)),
+        }
+    }
+}
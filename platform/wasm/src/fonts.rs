@@ -1,29 +1,469 @@
+use rustc_hash::FxHashMap;
 use typst::{
     foundations::Bytes,
-    text::{Font, FontBook},
+    text::{Font, FontBook, FontFlags, FontInfo},
     utils::LazyHash,
 };
+use typst_syntax::{LinkedNode, Source, SyntaxKind};
 
+use crate::{
+    bindings::{TypstFontCoverage, TypstFontFamily, TypstFontVariant, TypstMissingGlyph},
+    source::SpaceContext,
+};
+
+/// Scripts reported in [`TypstFontFamily::scripts`], each with a character
+/// that a font covering the script is expected to have.
+const SCRIPTS: &[(&str, char)] = &[
+    ("latin", 'a'),
+    ("greek", 'α'),
+    ("cyrillic", 'д'),
+    ("arabic", 'ع'),
+    ("hebrew", 'א'),
+    ("devanagari", 'क'),
+    ("thai", 'ก'),
+    ("han", '中'),
+    ("hiragana", 'あ'),
+    ("katakana", 'ア'),
+    ("hangul", '한'),
+];
+
+/// A font face known to the loader, whose body may not be loaded yet.
+#[derive(Debug, Clone)]
+pub struct FontSlot {
+    pub info: FontInfo,
+    /// The parsed face, or `None` until the app installs the file it is in.
+    pub font: Option<Font>,
+}
+
+impl FontSlot {
+    fn loaded(font: Font) -> Self {
+        Self {
+            info: font.info().clone(),
+            font: Some(font),
+        }
+    }
+
+    const fn lazy(info: FontInfo) -> Self {
+        Self { info, font: None }
+    }
+
+    fn is_face(&self, info: &FontInfo) -> bool {
+        self.info.family == info.family && self.info.variant == info.variant
+    }
+}
+
+/// A list of font slots and the book describing them, indexed alike.
 #[derive(Debug, Default)]
-pub struct FontLoader {
+pub struct FontSet {
     pub book: LazyHash<FontBook>,
-    pub fonts: Vec<Font>,
+    pub slots: Vec<FontSlot>,
+}
+
+impl FontSet {
+    fn push(&mut self, slot: FontSlot) {
+        self.book.push(slot.info.clone());
+        self.slots.push(slot);
+    }
+
+    fn from_slots<'a>(slots: impl IntoIterator<Item = &'a FontSlot>) -> Self {
+        let mut set = Self::default();
+
+        for slot in slots {
+            set.push(slot.clone());
+        }
+
+        set
+    }
+}
+
+/// Fonts installed for every space, plus fonts registered against a single
+/// space.
+///
+/// A space with fonts of its own gets its own [`FontSet`], layering them after
+/// the shared fonts. Fonts added to either later are appended to that set
+/// only, so no set is rebuilt as fonts arrive. Other spaces keep using the
+/// shared set, so their font book and fallback order are unaffected.
+///
+/// Faces can be registered from their [`FontInfo`] alone. Their slots take
+/// part in font selection right away, and are filled in when the file they
+/// are in gets installed.
+#[derive(Debug, Default)]
+pub struct FontLoader {
+    /// Fonts available to every space.
+    pub shared: FontSet,
+    /// Fonts registered against each space, and the combined view its notes
+    /// compile with.
+    spaces: FxHashMap<String, SpaceFonts>,
+}
+
+#[derive(Debug, Default)]
+struct SpaceFonts {
+    /// The space's own fonts.
+    own: Vec<FontSlot>,
+    /// The shared fonts followed by `own`. Fonts added to either list later
+    /// are appended.
+    view: FontSet,
+    /// Index in `view` of each shared slot.
+    shared_indices: Vec<usize>,
+    /// Index in `view` of each slot of `own`.
+    own_indices: Vec<usize>,
+}
+
+impl SpaceFonts {
+    fn new(shared: &[FontSlot]) -> Self {
+        let mut space = Self::default();
+        space.rebuild(shared);
+
+        space
+    }
+
+    fn rebuild(&mut self, shared: &[FontSlot]) {
+        self.view = FontSet::from_slots(shared.iter().chain(&self.own));
+        self.shared_indices = (0..shared.len()).collect();
+        self.own_indices = (shared.len()..self.view.slots.len()).collect();
+    }
+
+    /// Brings shared slot `index` into the view, after it was filled or
+    /// appended.
+    fn sync_shared(&mut self, shared: &[FontSlot], index: usize) {
+        if let Some(&view_index) = self.shared_indices.get(index) {
+            self.view.slots[view_index].font.clone_from(&shared[index].font);
+        } else {
+            self.shared_indices.push(self.view.slots.len());
+            self.view.push(shared[index].clone());
+        }
+    }
+
+    /// Fills the registered slot of `font`'s face, or appends the font.
+    fn install(&mut self, font: Font) {
+        let index = self
+            .own
+            .iter()
+            .position(|slot| slot.font.is_none() && slot.is_face(font.info()));
+
+        if let Some(index) = index {
+            self.view.slots[self.own_indices[index]].font = Some(font.clone());
+            self.own[index].font = Some(font);
+        } else {
+            self.push(FontSlot::loaded(font));
+        }
+    }
+
+    fn push(&mut self, slot: FontSlot) {
+        self.own_indices.push(self.view.slots.len());
+        self.view.push(slot.clone());
+        self.own.push(slot);
+    }
 }
 
 impl FontLoader {
     #[must_use]
     pub fn new() -> Self {
-        Self {
-            book: LazyHash::new(FontBook::new()),
-            fonts: vec![],
-        }
+        Self::default()
     }
 
-    pub fn install<T>(&mut self, bytes: T)
+    /// Installs every face in `bytes`, for a single space or all of them.
+    ///
+    /// Faces matching a registered slot fill it, others are appended.
+    pub fn install<T>(&mut self, space_id: Option<&str>, bytes: T)
     where T: AsRef<[u8]> + Send + Sync + 'static {
-        for font in Font::iter(Bytes::new(bytes)) {
-            self.book.push(font.info().clone());
-            self.fonts.push(font);
+        let fonts = Font::iter(Bytes::new(bytes));
+
+        match space_id {
+            Some(space_id) => {
+                let space = self.space_mut(space_id);
+
+                for font in fonts {
+                    space.install(font);
+                }
+            }
+            None => {
+                for font in fonts {
+                    let index = self
+                        .shared
+                        .slots
+                        .iter()
+                        .position(|slot| slot.font.is_none() && slot.is_face(font.info()));
+
+                    let index = if let Some(index) = index {
+                        self.shared.slots[index].font = Some(font);
+                        index
+                    } else {
+                        self.shared.push(FontSlot::loaded(font));
+                        self.shared.slots.len() - 1
+                    };
+
+                    self.sync_shared(index);
+                }
+            }
+        }
+    }
+
+    /// Registers faces without their bodies, for a single space or all of
+    /// them. Faces that are already known are skipped.
+    pub fn register(&mut self, space_id: Option<&str>, infos: Vec<FontInfo>) {
+        match space_id {
+            Some(space_id) => {
+                let space = self.space_mut(space_id);
+
+                for info in infos {
+                    if !space.own.iter().any(|slot| slot.is_face(&info)) {
+                        space.push(FontSlot::lazy(info));
+                    }
+                }
+            }
+            None => {
+                for info in infos {
+                    if !self.shared.slots.iter().any(|slot| slot.is_face(&info)) {
+                        self.shared.push(FontSlot::lazy(info));
+                        self.sync_shared(self.shared.slots.len() - 1);
+                    }
+                }
+            }
         }
     }
+
+    /// Removes a space's own faces of `family`, returning whether any were
+    /// removed. Shared fonts cannot be uninstalled per space.
+    pub fn uninstall_for_space(&mut self, space_id: &str, family: &str) -> bool {
+        let Some(space) = self.spaces.get_mut(space_id) else {
+            return false;
+        };
+
+        let len = space.own.len();
+        space
+            .own
+            .retain(|slot| !slot.info.family.eq_ignore_ascii_case(family));
+
+        if space.own.len() == len {
+            return false;
+        }
+
+        if space.own.is_empty() {
+            self.spaces.remove(space_id);
+        } else {
+            space.rebuild(&self.shared.slots);
+        }
+
+        true
+    }
+
+    /// The fonts a space's notes compile with.
+    #[must_use]
+    pub fn set(&self, space_id: Option<&str>) -> &FontSet {
+        space_id
+            .and_then(|space_id| self.spaces.get(space_id))
+            .map_or(&self.shared, |space| &space.view)
+    }
+
+    /// The space's own fonts, without the shared ones.
+    #[must_use]
+    pub fn space_fonts(&self, space_id: &str) -> &[FontSlot] {
+        self.spaces
+            .get(space_id)
+            .map_or(&[], |space| space.own.as_slice())
+    }
+
+    fn space_mut(&mut self, space_id: &str) -> &mut SpaceFonts {
+        self.spaces
+            .entry(space_id.to_string())
+            .or_insert_with(|| SpaceFonts::new(&self.shared.slots))
+    }
+
+    fn sync_shared(&mut self, index: usize) {
+        for space in self.spaces.values_mut() {
+            space.sync_shared(&self.shared.slots, index);
+        }
+    }
+}
+
+/// Describes every family in a font book.
+#[must_use]
+pub fn font_families(book: &FontBook) -> Vec<TypstFontFamily> {
+    book.families()
+        .map(|(family, indices)| {
+            let infos = indices
+                .filter_map(|index| book.info(index))
+                .collect::<Vec<_>>();
+
+            let scripts = SCRIPTS
+                .iter()
+                .filter(|(_, sample)| {
+                    infos
+                        .iter()
+                        .any(|info| info.coverage.contains(u32::from(*sample)))
+                })
+                .map(|(script, _)| (*script).to_string())
+                .collect();
+
+            TypstFontFamily {
+                family: family.to_string(),
+                variants: infos
+                    .iter()
+                    .map(|info| TypstFontVariant::from(info.variant))
+                    .collect(),
+                math: infos.iter().any(|info| info.flags.contains(FontFlags::MATH)),
+                monospace: infos
+                    .iter()
+                    .all(|info| info.flags.contains(FontFlags::MONOSPACE)),
+                scripts,
+            }
+        })
+        .collect()
+}
+
+/// Finds the characters of a note that have no glyph in the family
+/// configured for where they are used: the code font in raw text, the math
+/// font in equations, and the text font elsewhere.
+#[must_use]
+pub fn font_coverage(
+    raw_source: &Source,
+    space: &SpaceContext,
+    book: &FontBook,
+) -> TypstFontCoverage {
+    let text_family = space.font.as_str();
+    let math_family = space.math_font.as_deref().unwrap_or(text_family);
+    let code_family = space.code_font.as_deref().unwrap_or(text_family);
+
+    let mut coverage = TypstFontCoverage::default();
+
+    // Each family's faces, resolved once. The book keys families by their
+    // lowercase name.
+    let faces = [text_family, math_family, code_family].map(|family| {
+        let infos = book
+            .select_family(&family.to_lowercase())
+            .filter_map(|index| book.info(index))
+            .collect::<Vec<_>>();
+
+        (family, infos)
+    });
+
+    for (family, infos) in &faces {
+        if infos.is_empty() && !coverage.missing_families.iter().any(|missing| missing == family) {
+            coverage.missing_families.push((*family).to_string());
+        }
+    }
+
+    let raw_lines = raw_source.lines();
+    let covers = |family: &str, ch: char| {
+        faces
+            .iter()
+            .filter(|(name, _)| *name == family)
+            .flat_map(|(_, infos)| infos)
+            .any(|info| info.coverage.contains(u32::from(ch)))
+    };
+
+    let mut stack = vec![(LinkedNode::new(raw_source.root()), text_family)];
+
+    while let Some((curr, family)) = stack.pop() {
+        let family = match curr.kind() {
+            SyntaxKind::Raw => code_family,
+            SyntaxKind::Equation => math_family,
+            _ => family,
+        };
+
+        let checked = matches!(
+            curr.kind(),
+            SyntaxKind::Text | SyntaxKind::Str | SyntaxKind::MathText
+        );
+
+        // Missing families are already reported as a whole.
+        if checked && !coverage.missing_families.iter().any(|missing| missing == family) {
+            for (offset, ch) in curr.leaf_text().char_indices() {
+                if ch.is_whitespace() || covers(family, ch) {
+                    continue;
+                }
+
+                if let Some(glyph) = coverage
+                    .missing_glyphs
+                    .iter_mut()
+                    .find(|glyph| glyph.family == family && glyph.character.starts_with(ch))
+                {
+                    glyph.count += 1;
+                } else {
+                    let start = curr.offset() + offset;
+                    let end = start + ch.len_utf8();
+
+                    coverage.missing_glyphs.push(TypstMissingGlyph {
+                        character: ch.to_string(),
+                        family: family.to_string(),
+                        count: 1,
+                        range: raw_lines.byte_to_utf16(start).unwrap_or_default()
+                            ..raw_lines.byte_to_utf16(end).unwrap_or_default(),
+                    });
+                }
+            }
+        }
+
+        // Reversed, so children are visited in source order and each glyph's
+        // range is its first use.
+        stack.extend(curr.children().rev().map(|child| (child, family)));
+    }
+
+    coverage
+        .missing_glyphs
+        .sort_by_key(|glyph| glyph.range.start);
+
+    coverage
+}
+
+#[test]
+fn test_font_coverage() {
+    let bytes = include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf");
+    let infos = Font::iter(Bytes::new(bytes.to_vec())).map(|font| font.info().clone());
+    let book = FontBook::from_infos(infos);
+
+    let space = SpaceContext::new();
+    let source = Source::detached("Hi 中 `中` $x$ 文\n");
+    let coverage = font_coverage(&source, &space, &book);
+
+    assert_eq!(coverage.missing_families, ["New Computer Modern Math"]);
+
+    let glyphs = coverage
+        .missing_glyphs
+        .iter()
+        .map(|glyph| {
+            (
+                glyph.character.as_str(),
+                glyph.family.as_str(),
+                glyph.count,
+                glyph.range.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        glyphs,
+        [("中", "Maple Mono", 2, 3..4), ("文", "Maple Mono", 1, 13..14)]
+    );
+}
+
+#[test]
+fn test_space_view_follows_shared_fonts() {
+    let maple = include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf");
+    let math = include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf");
+    let math_info = Font::iter(Bytes::new(math)).next().unwrap().info().clone();
+
+    let mut loader = FontLoader::new();
+    loader.register(None, vec![math_info]);
+    loader.install(Some("space"), maple);
+
+    // A lazy shared slot is filled in the space's view too.
+    loader.install(None, math);
+    let view = loader.set(Some("space"));
+    assert_eq!(view.slots.len(), 2);
+    assert!(view.slots.iter().all(|slot| slot.font.is_some()));
+
+    // A new shared font is appended to the view, after the space's own.
+    loader.install(None, maple);
+    let view = loader.set(Some("space"));
+    assert_eq!(view.slots.len(), 3);
+    assert_eq!(view.book.info(2).unwrap().family, "Maple Mono");
+    assert_eq!(loader.set(None).slots.len(), 2);
+
+    // Uninstalling rebuilds the view with the shared fonts first.
+    loader.install(Some("space"), math);
+    assert!(loader.uninstall_for_space("space", "Maple Mono"));
+    let view = loader.set(Some("space"));
+    assert_eq!(view.slots.len(), 3);
+    assert_eq!(view.book.info(2).unwrap().family, "New Computer Modern Math");
 }
@@ -25,17 +25,26 @@
 //!
 //! ## Error Recovery
 //!
-//! Compile failures are handled by [`renderer::recovery`]. When the synth
-//! fails to compile, the recovery pass identifies the offending block,
-//! overwrites it with length-preserving whitespace, and retries. Math errors
-//! get finer treatment: the broken sub-expression is wrapped in a red-text
-//! marker rather than blanked, so the rest of the equation keeps rendering.
+//! Compile failures are handled by [`renderer::recovery`], shared by every
+//! render target. When the synth fails to compile, a recovery strategy
+//! neutralizes the offending part and the compile is retried. Math errors get
+//! the finest treatment: the broken sub-expression is wrapped in a red-text
+//! marker, so the rest of the equation keeps rendering. In markup and code,
+//! the innermost failing call, field access or variable is replaced by a
+//! marker showing its source. Errors neither can handle blank their block with
+//! length-preserving whitespace.
+//!
+//! Compile results list what was recovered in `recovered`, with the raw range,
+//! the strategy used and the error that triggered it.
 
 pub mod bindings;
 pub mod fonts;
+pub mod outline;
+pub mod package;
 pub mod renderer;
 pub mod source;
 pub mod state;
+pub mod style;
 pub mod theme;
 pub mod world;
 
@@ -0,0 +1,360 @@
+//! Document outlines: the headings, labels, figures and labelled equations of
+//! a note, nested under their headings.
+//!
+//! The outline is read from the introspector of the note's last successful
+//! compile, so numbering matches what was rendered. Before a note has ever
+//! compiled, it falls back to walking the raw syntax tree.
+
+use std::ops::Range;
+
+use ecow::eco_vec;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use typst::{
+    WorldExt,
+    foundations::{Content, NativeElement, Output, Selector, StyleChain},
+    introspection::Introspector,
+    math::EquationElem,
+    model::{FigureElem, HeadingElem},
+};
+use typst_syntax::{LinkedNode, Source, SyntaxKind};
+
+use crate::{bindings::map_raw_span, source::SourceContext, world::MnemoWorld};
+
+/// Builds the outline of a note.
+#[typst_macros::time]
+pub fn outline(context: &SourceContext, world: &MnemoWorld) -> Vec<TypstOutlineItem> {
+    let introspector = match (&context.paged_document, &context.html_document) {
+        (Some(document), _) => Some(Output::introspector(document)),
+        (None, Some(document)) => Some(Output::introspector(document)),
+        (None, None) => None,
+    };
+
+    match introspector {
+        Some(introspector) => outline_from_introspector(introspector, context, world),
+        None => {
+            context
+                .raw_source(world)
+                .map(outline_from_syntax)
+                .unwrap_or_default()
+        }
+    }
+}
+
+fn outline_from_introspector(
+    introspector: &dyn Introspector,
+    context: &SourceContext,
+    world: &MnemoWorld,
+) -> Vec<TypstOutlineItem> {
+    // Introspected elements are realized, so their synthesized fields, such as
+    // a heading's level, are set and need no styles.
+    let styles = StyleChain::default();
+
+    let mut elements = introspector.query(&Selector::Or(eco_vec![
+        HeadingElem::ELEM.select(),
+        FigureElem::ELEM.select(),
+    ]));
+    elements.extend(introspector.query_labelled());
+
+    // Labelled headings and figures are found twice.
+    let mut seen = FxHashSet::default();
+    let mut elements = elements
+        .into_iter()
+        .filter(|content| content.location().is_none_or(|location| seen.insert(location)))
+        .filter_map(|content| {
+            let span = content.span();
+            let range = world.range(span)?;
+
+            (span.id() == Some(context.synth_id) && !context.in_prelude(range.start))
+                .then_some((range.start, content))
+        })
+        .collect::<Vec<_>>();
+    elements.sort_by_key(|(offset, _)| *offset);
+
+    let mut builder = OutlineBuilder::default();
+
+    for (_, content) in elements {
+        let Some(range) = map_raw_span(content.span(), false, &[], context, world) else {
+            continue;
+        };
+        let label = content.label().map(|label| label.resolve().as_str().to_string());
+
+        if let Some(heading) = content.to_packed::<HeadingElem>() {
+            // The heading counter as displayed at the heading's location when
+            // it was laid out, with the numbering and offset in effect there.
+            let numbering = heading.numbers.as_ref().map(ToString::to_string);
+
+            builder.push_heading(
+                heading.resolve_level(styles).get(),
+                TypstOutlineItem::new(
+                    TypstOutlineKind::Heading,
+                    plain_text(&heading.body),
+                    label,
+                    numbering,
+                    range,
+                ),
+            );
+        } else if let Some(figure) = content.to_packed::<FigureElem>() {
+            let caption = figure
+                .caption
+                .get_ref(styles)
+                .as_ref()
+                .map(|caption| plain_text(&caption.body))
+                .unwrap_or_default();
+
+            builder.push(TypstOutlineItem::new(
+                TypstOutlineKind::Figure,
+                caption,
+                label,
+                None,
+                range,
+            ));
+        } else if let Some(label) = label {
+            let kind = if content.is::<EquationElem>() {
+                TypstOutlineKind::Equation
+            } else {
+                TypstOutlineKind::Label
+            };
+
+            builder.push(TypstOutlineItem::new(
+                kind,
+                plain_text(&content),
+                Some(label),
+                None,
+                range,
+            ));
+        }
+    }
+
+    builder.finish()
+}
+
+fn outline_from_syntax(raw_source: &Source) -> Vec<TypstOutlineItem> {
+    let raw_lines = raw_source.lines();
+    let utf16_range = |range: Range<usize>| {
+        raw_lines.byte_to_utf16(range.start).unwrap_or_default()
+            ..raw_lines.byte_to_utf16(range.end).unwrap_or_default()
+    };
+
+    let mut builder = OutlineBuilder::default();
+
+    // Children are pushed in reverse so nodes are visited in source order.
+    let mut stack = vec![LinkedNode::new(raw_source.root())];
+
+    while let Some(curr) = stack.pop() {
+        match curr.kind() {
+            SyntaxKind::Heading => {
+                let level = curr
+                    .children()
+                    .next()
+                    .map_or(1, |marker| marker.leaf_text().len());
+                let body = curr
+                    .children()
+                    .find(|child| child.kind() == SyntaxKind::Markup)
+                    .map(|body| raw_source.text()[body.range()].trim().to_string())
+                    .unwrap_or_default();
+
+                builder.push_heading(
+                    level,
+                    TypstOutlineItem::new(
+                        TypstOutlineKind::Heading,
+                        body,
+                        None,
+                        None,
+                        utf16_range(curr.range()),
+                    ),
+                );
+            }
+            SyntaxKind::Label => {
+                let label = curr.leaf_text().trim_start_matches('<').trim_end_matches('>');
+
+                let in_heading = curr
+                    .parent()
+                    .and_then(LinkedNode::parent)
+                    .is_some_and(|parent| parent.kind() == SyntaxKind::Heading);
+
+                if in_heading {
+                    builder.label_last_heading(label);
+
+                    continue;
+                }
+
+                // A label attaches to the closest preceding non-trivia sibling.
+                let target = curr.prev_sibling();
+                let kind = match target.as_ref().map(|target| target.kind()) {
+                    Some(SyntaxKind::Equation) => TypstOutlineKind::Equation,
+                    Some(SyntaxKind::FuncCall) if target.as_ref().is_some_and(is_figure) => {
+                        TypstOutlineKind::Figure
+                    }
+                    _ => TypstOutlineKind::Label,
+                };
+
+                let range = target.as_ref().map_or_else(|| curr.range(), |target| {
+                    target.offset()..curr.range().end
+                });
+
+                builder.push(TypstOutlineItem::new(
+                    kind,
+                    String::new(),
+                    Some(label.to_string()),
+                    None,
+                    utf16_range(range),
+                ));
+            }
+            _ => {}
+        }
+
+        let children = curr.children().collect::<Vec<_>>();
+        stack.extend(children.into_iter().rev());
+    }
+
+    builder.finish()
+}
+
+fn is_figure(node: &LinkedNode) -> bool {
+    node.children()
+        .next()
+        .is_some_and(|callee| {
+            callee.kind() == SyntaxKind::Ident && callee.leaf_text() == "figure"
+        })
+}
+
+fn plain_text(content: &Content) -> String {
+    content.plain_text().trim().to_string()
+}
+
+/// Nests outline items under the closest preceding heading of a lower level.
+#[derive(Default)]
+struct OutlineBuilder {
+    roots: Vec<TypstOutlineItem>,
+    /// Open headings and their levels, outermost first.
+    open: Vec<(usize, TypstOutlineItem)>,
+}
+
+impl OutlineBuilder {
+    fn push_heading(&mut self, level: usize, item: TypstOutlineItem) {
+        while self.open.last().is_some_and(|(open, _)| *open >= level) {
+            self.close();
+        }
+
+        self.open.push((level, item));
+    }
+
+    fn push(&mut self, item: TypstOutlineItem) {
+        match self.open.last_mut() {
+            Some((_, parent)) => parent.children.push(item),
+            None => self.roots.push(item),
+        }
+    }
+
+    fn label_last_heading(&mut self, label: &str) {
+        if let Some((_, heading)) = self.open.last_mut() {
+            heading.label = Some(label.to_string());
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some((_, item)) = self.open.pop() {
+            self.push(item);
+        }
+    }
+
+    fn finish(mut self) -> Vec<TypstOutlineItem> {
+        while !self.open.is_empty() {
+            self.close();
+        }
+
+        self.roots
+    }
+}
+
+/// An entry of a note's outline.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TypstOutlineItem {
+    pub kind: TypstOutlineKind,
+    /// The heading text, figure caption or equation text.
+    pub text: String,
+    /// The label attached to the element, without angle brackets.
+    pub label: Option<String>,
+    /// The heading's numbering as rendered, e.g. `1.2.`.
+    pub numbering: Option<String>,
+    /// UTF-16 range of the element in the raw source.
+    pub range: Range<usize>,
+    /// Items under this heading.
+    pub children: Vec<TypstOutlineItem>,
+}
+
+impl TypstOutlineItem {
+    const fn new(
+        kind: TypstOutlineKind,
+        text: String,
+        label: Option<String>,
+        numbering: Option<String>,
+        range: Range<usize>,
+    ) -> Self {
+        Self {
+            kind,
+            text,
+            label,
+            numbering,
+            range,
+            children: Vec::new(),
+        }
+    }
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypstOutlineKind {
+    Heading,
+    Label,
+    Figure,
+    Equation,
+}
+
+#[test]
+fn test_outline_numbering_as_rendered() {
+    let mut state = crate::state::TypstState::new();
+    state.install_font(
+        include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    let text = "#set heading(numbering: \"I.a\")\n= One\n== Sub <sub>\n= Two\n$ x $ <eq>\n";
+    state.compile_paged(&id, text, "");
+
+    let outline = state.outline(&id);
+    let summary = |item: &TypstOutlineItem| {
+        (
+            item.kind,
+            item.text.clone(),
+            item.label.clone(),
+            item.numbering.clone(),
+        )
+    };
+
+    assert_eq!(outline.len(), 2);
+    assert_eq!(
+        summary(&outline[0]),
+        (TypstOutlineKind::Heading, String::from("One"), None, Some(String::from("I")))
+    );
+    assert_eq!(
+        summary(&outline[0].children[0]),
+        (
+            TypstOutlineKind::Heading,
+            String::from("Sub"),
+            Some(String::from("sub")),
+            Some(String::from("I.a"))
+        )
+    );
+    assert_eq!(outline[1].numbering.as_deref(), Some("II"));
+    assert_eq!(outline[1].children[0].kind, TypstOutlineKind::Equation);
+    assert_eq!(outline[1].children[0].label.as_deref(), Some("eq"));
+}
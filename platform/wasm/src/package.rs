@@ -0,0 +1,759 @@
+//! Packages: unpacking and validating the `.tar.gz` files packages are
+//! distributed as, before any of their files reach the world, and `@local`
+//! packages made from a directory of project files.
+
+use std::{
+    fmt::Write,
+    io::{Cursor, Read},
+    path::{Component, Path},
+};
+
+use ecow::eco_format;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Archive, EntryType};
+use typst::{
+    foundations::Bytes,
+    syntax::{
+        FileId, VirtualPath,
+        package::{PackageManifest, PackageSpec, PackageVersion},
+    },
+};
+use typst_syntax::{RootedPath, Source, SyntaxNode, VirtualRoot, ast};
+
+use crate::{
+    state::{TypstError, TypstErrorKind},
+    world::MnemoWorld,
+};
+
+/// Largest compressed package archive accepted.
+const MAX_ARCHIVE_SIZE: usize = 16 * 1024 * 1024;
+/// Largest total size of a package's unpacked files accepted.
+const MAX_UNPACKED_SIZE: u64 = 64 * 1024 * 1024;
+/// Largest compressed bundle accepted.
+const MAX_BUNDLE_SIZE: usize = 4 * MAX_ARCHIVE_SIZE;
+/// Largest total size of a bundle's unpacked files accepted.
+const MAX_UNPACKED_BUNDLE_SIZE: u64 = 4 * MAX_UNPACKED_SIZE;
+
+/// Version of the bundle format written by [`export_bundle`].
+const BUNDLE_FORMAT: u32 = 1;
+/// Path of the bundle's index in a bundle archive.
+const BUNDLE_INDEX: &str = "bundle.toml";
+
+/// A package archive that was unpacked and checked against its spec.
+#[derive(Debug)]
+pub struct PackageArchive {
+    pub manifest: PackageManifest,
+    /// The archive's files by their path in the package, with `/` separators.
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl PackageArchive {
+    /// Unpacks a gzipped tarball and validates its `typst.toml` against `spec`.
+    pub fn unpack(spec: &PackageSpec, data: &[u8]) -> Result<Self, TypstError> {
+        let files = unpack_files(data, MAX_ARCHIVE_SIZE, MAX_UNPACKED_SIZE)?;
+
+        Self::from_files(spec, files)
+    }
+
+    /// Validates the `typst.toml` among a package's files against `spec`.
+    pub fn from_files(
+        spec: &PackageSpec,
+        files: Vec<(String, Vec<u8>)>,
+    ) -> Result<Self, TypstError> {
+        let manifest = files
+            .iter()
+            .find(|(path, _)| path == "typst.toml")
+            .ok_or_else(|| TypstError::new(TypstErrorKind::BadArchive, "missing typst.toml"))?;
+        let manifest = parse_manifest(&manifest.1)?;
+
+        validate_manifest(spec, &manifest)?;
+
+        let entrypoint = normalize_path(Path::new(manifest.package.entrypoint.as_str()));
+        let has_entrypoint = entrypoint
+            .is_some_and(|entrypoint| files.iter().any(|(path, _)| *path == entrypoint));
+
+        if !has_entrypoint {
+            return Err(TypstError::new(
+                TypstErrorKind::InvalidManifest,
+                eco_format!("entrypoint {} does not exist", manifest.package.entrypoint),
+            ));
+        }
+
+        Ok(Self { manifest, files })
+    }
+
+    /// Inserts the package's files into the world, Typst files as sources.
+    pub fn install(self, spec: &PackageSpec, world: &mut MnemoWorld) -> Result<(), TypstError> {
+        for (path, content) in self.files {
+            let vpath = VirtualPath::new(&path).map_err(|err| {
+                TypstError::new(TypstErrorKind::BadArchive, eco_format!("{path}: {err}"))
+            })?;
+            let id = FileId::new(RootedPath::new(VirtualRoot::Package(spec.clone()), vpath));
+
+            let is_typst = Path::new(&path)
+                .extension()
+                .is_some_and(|extension| extension == "typ");
+
+            if is_typst {
+                match String::from_utf8(content) {
+                    Ok(content) => world.insert_source(id, content),
+                    Err(err) => world.insert_file(id, Bytes::new(err.into_bytes())),
+                }
+            } else {
+                world.insert_file(id, Bytes::new(content));
+            }
+        }
+
+        world.packages.insert(spec.clone(), self.manifest);
+
+        Ok(())
+    }
+
+    /// Collects the files of an installed package back from the world.
+    #[must_use]
+    pub fn from_world(spec: &PackageSpec, world: &MnemoWorld) -> Option<Self> {
+        let manifest = world.packages.get(spec)?.clone();
+
+        let mut files = world
+            .files
+            .iter()
+            .filter(|(id, _)| matches!(id.root(), VirtualRoot::Package(root) if root == spec))
+            .map(|(id, file)| {
+                let path = id.vpath().get_with_slash().trim_start_matches('/').to_string();
+
+                (path, file.bytes().to_vec())
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Some(Self { manifest, files })
+    }
+
+    /// A SHA-256 hash of the package's paths and contents, independent of
+    /// the order the files are in.
+    #[must_use]
+    pub fn hash(&self) -> String {
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut hasher = Sha256::new();
+
+        for (path, content) in files {
+            hasher.update(path.as_bytes());
+            hasher.update([0]);
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(content);
+        }
+
+        hasher.finalize().iter().fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+    }
+}
+
+/// The index of a bundle, listing its packages and their hashes.
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleIndex {
+    format: u32,
+    packages: Vec<BundleEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleEntry {
+    /// The package spec, e.g. `@preview/cetz:0.4.2`.
+    spec: String,
+    /// The [`PackageArchive::hash`] of the package's files.
+    hash: String,
+}
+
+/// Writes one archive holding the installed packages `specs`, and every
+/// package they depend on.
+///
+/// Packages are stored under `<namespace>/<name>/<version>/`, next to a
+/// `bundle.toml` index with their hashes. `@local` packages are left out, as
+/// their files are part of the space.
+pub fn export_bundle(specs: &[PackageSpec], world: &MnemoWorld) -> Result<Vec<u8>, TypstError> {
+    let mut queue = specs.to_vec();
+    let mut packages = Vec::<(PackageSpec, PackageArchive)>::new();
+
+    while let Some(spec) = queue.pop() {
+        if world.local_packages.contains_key(&spec)
+            || packages.iter().any(|(bundled, _)| *bundled == spec)
+        {
+            continue;
+        }
+
+        let package = PackageArchive::from_world(&spec, world).ok_or_else(|| {
+            TypstError::new(
+                TypstErrorKind::PackageNotInstalled,
+                eco_format!("{spec} is not installed"),
+            )
+        })?;
+
+        for (path, content) in &package.files {
+            if Path::new(path).extension().is_some_and(|extension| extension == "typ")
+                && let Ok(text) = std::str::from_utf8(content)
+            {
+                queue.extend(imported_packages(&Source::detached(text)));
+            }
+        }
+
+        packages.push((spec, package));
+    }
+
+    let index = BundleIndex {
+        format: BUNDLE_FORMAT,
+        packages: packages
+            .iter()
+            .map(|(spec, package)| BundleEntry {
+                spec: spec.to_string(),
+                hash: package.hash(),
+            })
+            .collect(),
+    };
+    let index = toml::to_string(&index)
+        .map_err(|err| TypstError::new(TypstErrorKind::BadArchive, eco_format!("{err}")))?;
+
+    let io_error =
+        |err: std::io::Error| TypstError::new(TypstErrorKind::BadArchive, eco_format!("{err}"));
+
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut append = |path: &str, content: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+
+        builder.append_data(&mut header, path, content).map_err(io_error)
+    };
+
+    append(BUNDLE_INDEX, index.as_bytes())?;
+
+    for (spec, package) in &packages {
+        for (path, content) in &package.files {
+            append(&format!("{}/{path}", bundle_dir(spec)), content)?;
+        }
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(io_error)
+}
+
+/// Installs every package of a bundle written by [`export_bundle`].
+///
+/// Nothing is installed unless every package matches its hash and passes the
+/// same checks as a single package archive.
+pub fn import_bundle(data: &[u8], world: &mut MnemoWorld) -> Result<Vec<PackageSpec>, TypstError> {
+    let mut files = unpack_files(data, MAX_BUNDLE_SIZE, MAX_UNPACKED_BUNDLE_SIZE)?;
+
+    let index = files
+        .iter()
+        .position(|(path, _)| path == BUNDLE_INDEX)
+        .map(|index| files.swap_remove(index).1)
+        .ok_or_else(|| TypstError::new(TypstErrorKind::BadArchive, "missing bundle.toml"))?;
+    let index = std::str::from_utf8(&index)
+        .ok()
+        .and_then(|index| toml::from_str::<BundleIndex>(index).ok())
+        .ok_or_else(|| TypstError::new(TypstErrorKind::BadArchive, "invalid bundle.toml"))?;
+
+    if index.format != BUNDLE_FORMAT {
+        return Err(TypstError::new(
+            TypstErrorKind::BadArchive,
+            eco_format!("unsupported bundle format {}", index.format),
+        ));
+    }
+
+    let mut packages = Vec::new();
+
+    for entry in index.packages {
+        let spec = entry.spec.parse::<PackageSpec>().map_err(|err| {
+            TypstError::new(TypstErrorKind::BadArchive, eco_format!("bundle.toml: {err}"))
+        })?;
+
+        let prefix = format!("{}/", bundle_dir(&spec));
+        let package_files = files
+            .iter()
+            .filter_map(|(path, content)| {
+                let path = path.strip_prefix(&prefix)?;
+
+                Some((path.to_string(), content.clone()))
+            })
+            .collect();
+
+        let package = PackageArchive::from_files(&spec, package_files)?;
+
+        if package.hash() != entry.hash {
+            return Err(TypstError::new(
+                TypstErrorKind::HashMismatch,
+                eco_format!("{spec} does not match its hash"),
+            ));
+        }
+
+        packages.push((spec, package));
+    }
+
+    let mut installed = Vec::new();
+
+    for (spec, package) in packages {
+        package.install(&spec, world)?;
+        installed.push(spec);
+    }
+
+    Ok(installed)
+}
+
+fn bundle_dir(spec: &PackageSpec) -> String {
+    format!("{}/{}/{}", spec.namespace, spec.name, spec.version)
+}
+
+/// A directory of project files to be registered as `@local/<name>:<version>`.
+#[derive(Debug)]
+pub struct LocalPackage {
+    pub spec: PackageSpec,
+    /// The project directory holding the package's files, without leading or
+    /// trailing slashes.
+    pub dir: String,
+    pub manifest: PackageManifest,
+    /// The generated `typst.toml`.
+    pub toml: String,
+}
+
+impl LocalPackage {
+    pub fn new(
+        name: &str,
+        version: &str,
+        dir: &str,
+        entrypoint: &str,
+    ) -> Result<Self, TypstError> {
+        let invalid = |message| TypstError::new(TypstErrorKind::InvalidArgument, message);
+
+        if !typst_syntax::is_ident(name) {
+            return Err(invalid(eco_format!("`{name}` is not a valid package name")));
+        }
+
+        let version = version.parse::<PackageVersion>().map_err(invalid)?;
+        let dir = normalize_path(Path::new(dir))
+            .ok_or_else(|| invalid(eco_format!("`{dir}` is not a project directory")))?;
+        let entrypoint = normalize_path(Path::new(entrypoint))
+            .ok_or_else(|| invalid(eco_format!("`{entrypoint}` is not a path in the package")))?;
+
+        let spec = PackageSpec {
+            namespace: "local".into(),
+            name: name.into(),
+            version,
+        };
+
+        let toml = format!(
+            "[package]\nname = {}\nversion = {}\nentrypoint = {}\n",
+            toml::Value::from(name),
+            toml::Value::from(version.to_string()),
+            toml::Value::from(entrypoint),
+        );
+        let manifest = parse_manifest(toml.as_bytes())?;
+
+        Ok(Self {
+            spec,
+            dir,
+            manifest,
+            toml,
+        })
+    }
+
+    /// The id of a file in the package.
+    #[must_use]
+    pub fn file_id(&self, path: &str) -> Option<FileId> {
+        Some(FileId::new(RootedPath::new(
+            VirtualRoot::Package(self.spec.clone()),
+            VirtualPath::new(path).ok()?,
+        )))
+    }
+}
+
+/// Package specs a source imports or includes, in order of appearance.
+#[must_use]
+pub fn imported_packages(source: &Source) -> Vec<PackageSpec> {
+    let mut specs = Vec::new();
+    collect_imported_packages(source.root(), &mut specs);
+
+    specs
+}
+
+fn collect_imported_packages(node: &SyntaxNode, specs: &mut Vec<PackageSpec>) {
+    let path = match node.cast::<ast::ModuleImport>() {
+        Some(import) => Some(import.source()),
+        None => node.cast::<ast::ModuleInclude>().map(|include| include.source()),
+    };
+
+    if let Some(ast::Expr::Str(path)) = path
+        && let Ok(spec) = path.get().parse::<PackageSpec>()
+        && !specs.contains(&spec)
+    {
+        specs.push(spec);
+    }
+
+    for child in node.children() {
+        collect_imported_packages(child, specs);
+    }
+}
+
+fn unpack_files(
+    data: &[u8],
+    max_size: usize,
+    max_unpacked_size: u64,
+) -> Result<Vec<(String, Vec<u8>)>, TypstError> {
+    if data.len() > max_size {
+        return Err(TypstError::new(
+            TypstErrorKind::BadArchive,
+            eco_format!("archive is larger than {} MiB", max_size / 1024 / 1024),
+        ));
+    }
+
+    let bad_archive =
+        |err: std::io::Error| TypstError::new(TypstErrorKind::BadArchive, eco_format!("{err}"));
+
+    let decoder = flate2::read::GzDecoder::new(Cursor::new(data));
+    let mut archive = Archive::new(decoder);
+
+    let mut files = Vec::new();
+    let mut unpacked_size = 0;
+
+    for entry in archive.entries().map_err(bad_archive)? {
+        let entry = entry.map_err(bad_archive)?;
+
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous => {}
+            EntryType::Directory | EntryType::XGlobalHeader | EntryType::XHeader => continue,
+            entry_type => {
+                return Err(TypstError::new(
+                    TypstErrorKind::BadArchive,
+                    eco_format!("unsupported entry type {entry_type:?}"),
+                ));
+            }
+        }
+
+        let raw_path = entry.path().map_err(bad_archive)?.into_owned();
+        let path = normalize_path(&raw_path).ok_or_else(|| {
+            TypstError::new(
+                TypstErrorKind::BadArchive,
+                eco_format!("path {} escapes the package", raw_path.display()),
+            )
+        })?;
+
+        // The header size can lie, so the read itself is bounded too.
+        let remaining = max_unpacked_size - unpacked_size;
+        let mut content = Vec::new();
+        entry
+            .take(remaining + 1)
+            .read_to_end(&mut content)
+            .map_err(bad_archive)?;
+
+        unpacked_size += content.len() as u64;
+        if unpacked_size > max_unpacked_size {
+            return Err(TypstError::new(
+                TypstErrorKind::BadArchive,
+                eco_format!("unpacked files exceed {} MiB", max_unpacked_size / 1024 / 1024),
+            ));
+        }
+
+        files.push((path, content));
+    }
+
+    Ok(files)
+}
+
+fn parse_manifest(bytes: &[u8]) -> Result<PackageManifest, TypstError> {
+    let text = std::str::from_utf8(bytes).map_err(|err| {
+        TypstError::new(TypstErrorKind::InvalidManifest, eco_format!("typst.toml: {err}"))
+    })?;
+
+    toml::from_str(text).map_err(|err| {
+        TypstError::new(TypstErrorKind::InvalidManifest, eco_format!("typst.toml: {err}"))
+    })
+}
+
+fn validate_manifest(spec: &PackageSpec, manifest: &PackageManifest) -> Result<(), TypstError> {
+    let package = &manifest.package;
+
+    if package.name != spec.name {
+        return Err(TypstError::new(
+            TypstErrorKind::ManifestMismatch,
+            eco_format!("package manifest contains mismatched name `{}`", package.name),
+        ));
+    }
+
+    if package.version != spec.version {
+        return Err(TypstError::new(
+            TypstErrorKind::ManifestMismatch,
+            eco_format!("package manifest contains mismatched version {}", package.version),
+        ));
+    }
+
+    if let Some(required) = &package.compiler {
+        let current = PackageVersion::compiler();
+
+        if !current.matches_ge(required) {
+            return Err(TypstError::new(
+                TypstErrorKind::IncompatibleCompiler,
+                eco_format!(
+                    "package requires Typst {required} or newer (current version is {current})"
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns an archive path into a `/`-separated path inside the package, or
+/// `None` if it is absolute or leaves the package through `..`.
+fn normalize_path(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(..) => return None,
+        }
+    }
+
+    if parts.is_empty() {
+        return None;
+    }
+
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::fast(),
+    ));
+
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+
+        // `append_data` refuses `..`, so the name is written directly.
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_cksum();
+
+        builder.append(&header, *content).unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+#[cfg(test)]
+const MANIFEST: &[u8] = br#"
+[package]
+name = "example"
+version = "0.1.0"
+entrypoint = "src/lib.typ"
+"#;
+
+#[test]
+fn test_normalize_path() {
+    assert_eq!(normalize_path(Path::new("./src/lib.typ")).as_deref(), Some("src/lib.typ"));
+    assert_eq!(normalize_path(Path::new("../lib.typ")), None);
+    assert_eq!(normalize_path(Path::new("src/../../lib.typ")), None);
+    assert_eq!(normalize_path(Path::new("/etc/passwd")), None);
+}
+
+#[test]
+fn test_unpack_valid_package() {
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let data = archive(&[("typst.toml", MANIFEST), ("src/lib.typ", b"#let x = 1")]);
+
+    let package = PackageArchive::unpack(&spec, &data).unwrap();
+
+    assert_eq!(package.files.len(), 2);
+}
+
+#[test]
+fn test_unpack_rejects_traversal() {
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let data = archive(&[("typst.toml", MANIFEST), ("../lib.typ", b"")]);
+
+    let err = PackageArchive::unpack(&spec, &data).unwrap_err();
+
+    assert_eq!(err.kind, TypstErrorKind::BadArchive);
+}
+
+#[test]
+fn test_unpack_rejects_mismatched_version() {
+    let spec = "@preview/example:0.2.0".parse().unwrap();
+    let data = archive(&[("typst.toml", MANIFEST), ("src/lib.typ", b"")]);
+
+    let err = PackageArchive::unpack(&spec, &data).unwrap_err();
+
+    assert_eq!(err.kind, TypstErrorKind::ManifestMismatch);
+}
+
+#[test]
+fn test_imported_packages() {
+    let source = Source::detached(
+        r#"
+        #import "@preview/cetz:0.4.2": canvas
+        #import "utils.typ"
+        #include "@preview/oxifmt:1.0.0"
+        #import "@preview/cetz:0.4.2"
+        "#,
+    );
+
+    let specs = imported_packages(&source)
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    assert_eq!(specs, ["@preview/cetz:0.4.2", "@preview/oxifmt:1.0.0"]);
+}
+
+#[test]
+fn test_local_package_reads_project_files() {
+    use typst::World;
+
+    let package =
+        LocalPackage::new("template", "0.1.0", "spaces/a/template/", "lib.typ").unwrap();
+    let entrypoint = package.file_id("lib.typ").unwrap();
+    let manifest = package.file_id("typst.toml").unwrap();
+
+    let mut world = MnemoWorld::default();
+    world.register_local_package(package);
+
+    assert!(world.source(entrypoint).is_err());
+    assert_eq!(world.requested_sources.len(), 1);
+
+    let project = FileId::new(RootedPath::new(
+        VirtualRoot::Project,
+        VirtualPath::new("spaces/a/template/lib.typ").unwrap(),
+    ));
+
+    world.insert_source(project, String::from("#let x = 1"));
+    assert_eq!(world.source(entrypoint).unwrap().text(), "#let x = 1");
+
+    world.insert_source(project, String::from("#let x = 2"));
+    assert_eq!(world.source(entrypoint).unwrap().text(), "#let x = 2");
+
+    let manifest = world.file(manifest).unwrap();
+    assert!(std::str::from_utf8(&manifest).unwrap().contains(r#"entrypoint = "lib.typ""#));
+}
+
+#[test]
+fn test_bundle_round_trip() {
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let data = archive(&[("typst.toml", MANIFEST), ("src/lib.typ", b"#let x = 1")]);
+
+    let mut world = MnemoWorld::default();
+    PackageArchive::unpack(&spec, &data)
+        .unwrap()
+        .install(&spec, &mut world)
+        .unwrap();
+
+    let bundle = export_bundle(std::slice::from_ref(&spec), &world).unwrap();
+
+    let mut world = MnemoWorld::default();
+    let installed = import_bundle(&bundle, &mut world).unwrap();
+
+    assert_eq!(installed, std::slice::from_ref(&spec));
+    assert!(PackageArchive::from_world(&spec, &world).is_some());
+}
+
+#[test]
+fn test_export_bundle_requires_dependencies() {
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let data = archive(&[
+        ("typst.toml", MANIFEST),
+        ("src/lib.typ", br#"#import "@preview/missing:1.0.0""#),
+    ]);
+
+    let mut world = MnemoWorld::default();
+    PackageArchive::unpack(&spec, &data)
+        .unwrap()
+        .install(&spec, &mut world)
+        .unwrap();
+
+    let err = export_bundle(&[spec], &world).unwrap_err();
+
+    assert_eq!(err.kind, TypstErrorKind::PackageNotInstalled);
+}
+
+#[test]
+fn test_unpack_rejects_missing_entrypoint() {
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let data = archive(&[("typst.toml", MANIFEST)]);
+
+    let err = PackageArchive::unpack(&spec, &data).unwrap_err();
+
+    assert_eq!(err.kind, TypstErrorKind::InvalidManifest);
+}
+
+#[test]
+fn test_import_bundle_rejects_tampering() {
+    let dependency = "@preview/dependency:0.1.0".parse().unwrap();
+    let manifest = br#"
+[package]
+name = "dependency"
+version = "0.1.0"
+entrypoint = "lib.typ"
+"#;
+    let spec = "@preview/example:0.1.0".parse().unwrap();
+    let lib = br#"#import "@preview/dependency:0.1.0""#;
+
+    let mut world = MnemoWorld::default();
+    for (spec, data) in [
+        (&dependency, archive(&[("typst.toml", manifest), ("lib.typ", b"#let y = 1")])),
+        (&spec, archive(&[("typst.toml", MANIFEST), ("src/lib.typ", lib)])),
+    ] {
+        PackageArchive::unpack(spec, &data)
+            .unwrap()
+            .install(spec, &mut world)
+            .unwrap();
+    }
+
+    let bundle = export_bundle(std::slice::from_ref(&spec), &world).unwrap();
+    let files = unpack_files(&bundle, MAX_BUNDLE_SIZE, MAX_UNPACKED_BUNDLE_SIZE).unwrap();
+    let index = &files.iter().find(|(path, _)| path == BUNDLE_INDEX).unwrap().1;
+    let index = std::str::from_utf8(index).unwrap();
+
+    // Repacks the bundle, changing one file's content or the index.
+    let repack = |tampered: &str, content: &[u8], index: &str| {
+        let files = files
+            .iter()
+            .map(|(path, original)| {
+                let content = match path.as_str() {
+                    BUNDLE_INDEX => index.as_bytes(),
+                    path if path == tampered => content,
+                    _ => original.as_slice(),
+                };
+
+                (path.as_str(), content)
+            })
+            .collect::<Vec<_>>();
+
+        archive(&files)
+    };
+
+    let hash = PackageArchive::from_world(&dependency, &world).unwrap().hash();
+
+    for bundle in [
+        repack("preview/dependency/0.1.0/lib.typ", b"#let y = 2", index),
+        repack("", b"", &index.replace(&hash, &"0".repeat(hash.len()))),
+    ] {
+        let mut world = MnemoWorld::default();
+        let err = import_bundle(&bundle, &mut world).unwrap_err();
+
+        assert_eq!(err.kind, TypstErrorKind::HashMismatch);
+        assert!(PackageArchive::from_world(&spec, &world).is_none());
+        assert!(PackageArchive::from_world(&dependency, &world).is_none());
+    }
+}
@@ -0,0 +1,506 @@
+//! Whole-space export: compiles many notes into a single document.
+//!
+//! Every note is copied into a raw source of the export's own, and their
+//! contents are spliced into one synth owned by the export's
+//! [`SourceContext`], after a generated table of contents. Each note becomes an [`ExportSection`] with its own
+//! `IndexMapper` expressed in combined synth coordinates, so diagnostics can
+//! be traced back to the note they came from.
+//!
+//! A note's content is wrapped in a content block with `heading(offset:1)`,
+//! which scopes its `let` bindings and `set` rules to that note and nests its
+//! headings under the generated note title.
+
+use std::ops::Range;
+
+use ecow::{EcoVec, eco_format, eco_vec};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use typst::{
+    compile,
+    diag::{Severity, SourceDiagnostic},
+    foundations::Output,
+    model::Document,
+    syntax::{FileId, VirtualPath},
+};
+use typst_html::{HtmlDocument, HtmlOptions};
+use typst_layout::PagedDocument;
+use typst_pdf::PdfOptions;
+use typst_syntax::{RootedPath, VirtualRoot};
+
+use crate::{
+    bindings::{TypstDiagnostic, TypstDiagnosticSeverity, TypstFileId, map_synth_span},
+    renderer::{
+        paged::pdf::PdfExportOptions,
+        recovery::{
+            BlockBlanking, Recovery, RecoveryRegions, apply_to_context, synth_error_ranges,
+        },
+    },
+    source::{AstBlock, RenderTarget, SourceContext, SynthResult, sync_source_context},
+    state::{TypstError, TypstErrorKind, TypstState},
+    utils::typst_string,
+};
+
+/// Compiles the given notes into a single PDF document.
+#[typst_macros::time]
+pub fn export_pdf(
+    id: &TypstFileId,
+    notes: Vec<ExportNote>,
+    prelude: &str,
+    options: PdfExportOptions,
+    state: &mut TypstState,
+) -> Result<ExportPdfRender, TypstError> {
+    state.get_source_context_mut(id).pdf_options = options;
+
+    let sections = sync_export_sections(*id, notes, prelude, RenderTarget::Pdf, state)?;
+    let (document, mut diagnostics) = compile_sections::<PagedDocument>(*id, &sections, state);
+
+    let bytes = document.and_then(|document| {
+        match typst_pdf::pdf(&document, &PdfOptions::default()) {
+            Ok(bytes) => Some(bytes),
+            Err(source_diagnostics) => {
+                diagnostics.extend(map_export_diagnostics(
+                    source_diagnostics,
+                    *id,
+                    &sections,
+                    state,
+                ));
+
+                None
+            }
+        }
+    });
+
+    remove_sections(sections, state);
+
+    Ok(ExportPdfRender { bytes, diagnostics })
+}
+
+/// Compiles the given notes into a single HTML document.
+#[typst_macros::time]
+pub fn export_html(
+    id: &TypstFileId,
+    notes: Vec<ExportNote>,
+    prelude: &str,
+    state: &mut TypstState,
+) -> Result<ExportHtmlRender, TypstError> {
+    let sections = sync_export_sections(*id, notes, prelude, RenderTarget::Html, state)?;
+    let (document, mut diagnostics) = compile_sections::<HtmlDocument>(*id, &sections, state);
+
+    let html = document.and_then(|document| {
+        match typst_html::html(&document, &HtmlOptions::default()) {
+            Ok(html) => Some(html),
+            Err(source_diagnostics) => {
+                diagnostics.extend(map_export_diagnostics(
+                    source_diagnostics,
+                    *id,
+                    &sections,
+                    state,
+                ));
+
+                None
+            }
+        }
+    });
+
+    remove_sections(sections, state);
+
+    Ok(ExportHtmlRender { html, diagnostics })
+}
+
+/// Builds the combined synth for an export and writes it to the export's
+/// synth source.
+///
+/// Fails without touching the state if a note's path is not a valid virtual
+/// path.
+#[typst_macros::time]
+fn sync_export_sections(
+    id: TypstFileId,
+    notes: Vec<ExportNote>,
+    prelude: &str,
+    render_target: RenderTarget,
+    state: &mut TypstState,
+) -> Result<Vec<ExportSection>, TypstError> {
+    let note_ids = notes
+        .iter()
+        .map(|note| {
+            let path = VirtualPath::new(&note.path).map_err(|err| {
+                TypstError::new(
+                    TypstErrorKind::InvalidArgument,
+                    eco_format!("invalid note path {:?}: {err}", note.path),
+                )
+            })?;
+            Ok(FileId::new(RootedPath::new(VirtualRoot::Project, path.with_extension("typ"))))
+        })
+        .collect::<Result<Vec<_>, TypstError>>()?;
+
+    let mut synth = state.prelude(&id, render_target) + prelude + "\n";
+    synth += "#outline()\n";
+
+    let context = state.source_context_map.get(&id).unwrap();
+    let synth_id = context.synth_id;
+    let raw_id = context.raw_id;
+    let space_id = context.space_id.clone();
+
+    let mut sections = Vec::with_capacity(notes.len());
+
+    for (index, (note, note_id)) in notes.into_iter().zip(note_ids).enumerate() {
+        let mut section_context = SourceContext::new(note_id, space_id.clone());
+
+        let text = match note.text {
+            Some(text) => text,
+            None => {
+                section_context
+                    .raw_source(&state.world)
+                    .map(|source| source.text().to_string())
+                    .unwrap_or_default()
+            }
+        };
+
+        // The section gets a raw source of its own, so the export never
+        // touches the raw source of an open note, and its synth is the
+        // export's combined synth.
+        section_context.synth_id = synth_id;
+        section_context.raw_id = FileId::new(RootedPath::new(
+            synth_id.root().clone(),
+            synth_id.vpath().with_extension(&format!("{index}.$.typ")),
+        ));
+        state
+            .world
+            .insert_source(section_context.raw_id, String::new());
+
+        if render_target == RenderTarget::Pdf {
+            synth += "#pagebreak(weak:true)\n";
+        }
+
+        synth += &format!("#heading(level:1,{})\n", typst_string(&note.title));
+
+        if let Some(date) = &note.date {
+            synth += &format!(
+                "#align(right,text(size:14pt,fill:theme.on-primary-container,{}))\n",
+                typst_string(date)
+            );
+        }
+
+        synth += "#[\n#set heading(offset:1)\n";
+        let start = synth.len();
+
+        let SynthResult {
+            synth: section_synth,
+            blocks,
+            ..
+        } = sync_source_context(&text, synth, &mut section_context, &mut state.world);
+        synth = section_synth;
+
+        let end = synth.len();
+        synth += "]\n";
+
+        sections.push(ExportSection {
+            context: section_context,
+            blocks,
+            synth_range: start..end,
+        });
+    }
+
+    // `sync_source_context` points the world at each note in turn.
+    state.world.synth_id = Some(synth_id);
+    state.world.raw_id = Some(raw_id);
+
+    let context = state.source_context_map.get_mut(&id).unwrap();
+    context
+        .synth_source_mut(&mut state.world)
+        .unwrap()
+        .replace(&synth);
+    context.unstable_synth = synth;
+
+    Ok(sections)
+}
+
+/// Compiles the combined synth, blanking the failing blocks of each note until
+/// it succeeds or recovery gives up.
+#[typst_macros::time]
+fn compile_sections<D: Document + Output>(
+    id: TypstFileId,
+    sections: &[ExportSection],
+    state: &mut TypstState,
+) -> (Option<D>, Vec<ExportDiagnostic>) {
+    let mut document = None;
+
+    let mut diagnostics = Vec::new();
+    let mut compiled_warnings = None;
+
+    // Markers would have to be folded into every note's own index mapper, so
+    // failing blocks are only blanked.
+    let mut recovery = Recovery::new(vec![Box::new(BlockBlanking)]);
+    let regions = section_regions(sections);
+
+    while document.is_none() {
+        let compiled = compile::<D>(&state.world);
+        compiled_warnings = Some(compiled.warnings);
+
+        match compiled.output {
+            Ok(compiled_document) => document = Some(compiled_document),
+            Err(source_diagnostics) => {
+                diagnostics.extend(map_export_diagnostics(
+                    source_diagnostics.clone(),
+                    id,
+                    sections,
+                    state,
+                ));
+
+                let context = state.source_context_map.get_mut(&id).unwrap();
+                let errors = synth_error_ranges(&source_diagnostics, context, &state.world);
+
+                let Some(synth) = context.synth_source(&state.world) else {
+                    break;
+                };
+                let Some((_, edits)) = recovery.next_edits(synth.text(), &errors, &regions) else {
+                    break;
+                };
+
+                apply_to_context(&edits, context, &mut state.world);
+            }
+        }
+    }
+
+    if let Some(warnings) = compiled_warnings {
+        diagnostics.extend(map_export_diagnostics(warnings, id, sections, state));
+    }
+
+    (document, diagnostics)
+}
+
+/// Removes the raw sources of an export's sections once its diagnostics are
+/// mapped.
+fn remove_sections(sections: Vec<ExportSection>, state: &mut TypstState) {
+    for section in sections {
+        state.world.remove_source(&section.context.raw_id);
+    }
+}
+
+/// The blocks of every note, in combined synth coordinates.
+fn section_regions(sections: &[ExportSection]) -> RecoveryRegions {
+    let blocks = sections
+        .iter()
+        .flat_map(|section| {
+            section.blocks.iter().map(|block| {
+                let start = section.context.map_raw_to_synth_from_left(block.range.start);
+                let end = section.context.map_raw_to_synth_from_right(block.range.end);
+
+                start..end
+            })
+        })
+        .collect();
+
+    RecoveryRegions {
+        blocks,
+        equations: Vec::new(),
+    }
+}
+
+/// Maps diagnostics in the combined synth back to the note they came from.
+///
+/// Diagnostics outside of every note (in the prelude or a generated heading)
+/// are reported without a note and with an empty range.
+fn map_export_diagnostics(
+    source_diagnostics: EcoVec<SourceDiagnostic>,
+    id: TypstFileId,
+    sections: &[ExportSection],
+    state: &TypstState,
+) -> Vec<ExportDiagnostic> {
+    let context = state.get_source_context(&id);
+
+    source_diagnostics
+        .into_iter()
+        .filter_map(|diagnostic| {
+            let synth_range = map_synth_span(
+                diagnostic.span,
+                diagnostic.severity == Severity::Error,
+                &diagnostic.trace,
+                context,
+                &state.world,
+            );
+
+            let note = synth_range.and_then(|synth_range| {
+                sections
+                    .iter()
+                    .position(|section| section.synth_range.contains(&synth_range.start))
+            });
+
+            let Some(note) = note else {
+                if diagnostic.severity != Severity::Error {
+                    return None;
+                }
+
+                return Some(ExportDiagnostic {
+                    note: None,
+                    diagnostic: TypstDiagnostic {
+                        range: 0..0,
+                        path: None,
+                        severity: TypstDiagnosticSeverity::from_severity(diagnostic.severity),
+                        message: diagnostic.message.to_string(),
+                        hints: diagnostic
+                            .hints
+                            .into_iter()
+                            .map(|s| s.v.to_string())
+                            .collect(),
+                    },
+                });
+            };
+
+            TypstDiagnostic::from_diagnostics(
+                eco_vec![diagnostic],
+                &sections[note].context,
+                &state.world,
+            )
+            .into_vec()
+            .pop()
+            .map(|diagnostic| {
+                ExportDiagnostic {
+                    note: Some(note),
+                    diagnostic,
+                }
+            })
+        })
+        .collect()
+}
+
+/// One note spliced into an export's combined synth.
+#[derive(Debug)]
+pub struct ExportSection {
+    /// Context pairing a copy of the note's raw source with the combined
+    /// synth.
+    pub context: SourceContext,
+    /// The note's top-level blocks, in raw bytes.
+    pub blocks: Vec<AstBlock>,
+    /// Byte range of the note's content in the combined synth.
+    pub synth_range: Range<usize>,
+}
+
+/// A note to include in an export, in the order it should appear.
+#[derive(Debug, Clone, Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ExportNote {
+    /// The path the note was registered with in `createSourceId`.
+    pub path: String,
+    /// Title shown in the note's heading and the table of contents.
+    pub title: String,
+    /// Date shown under the note's heading, already formatted by the caller.
+    pub date: Option<String>,
+    /// The note's text. When unset, the note's last synced text is used.
+    pub text: Option<String>,
+}
+
+/// A diagnostic produced while exporting, attributed to a note.
+#[derive(Debug, Clone, Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ExportDiagnostic {
+    /// Index of the note in the exported list, or `None` if the diagnostic
+    /// does not belong to any note.
+    pub note: Option<usize>,
+    /// The diagnostic, with its range in the note's raw UTF-16 coordinates.
+    pub diagnostic: TypstDiagnostic,
+}
+
+/// Result of exporting notes to PDF.
+#[derive(Debug)]
+pub struct ExportPdfRender {
+    /// The encoded PDF, if compilation and export succeeded.
+    pub bytes: Option<Vec<u8>>,
+    /// Diagnostics and warnings produced during rendering.
+    pub diagnostics: Vec<ExportDiagnostic>,
+}
+
+/// Result of exporting notes to HTML.
+#[derive(Debug)]
+pub struct ExportHtmlRender {
+    /// The rendered HTML document, if successful.
+    pub html: Option<String>,
+    /// Diagnostics and warnings produced during rendering.
+    pub diagnostics: Vec<ExportDiagnostic>,
+}
+
+#[test]
+fn test_export_rejects_invalid_note_path() {
+    let mut state = TypstState::new();
+    let id = state.create_source_id("export", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    let note = ExportNote {
+        path: String::from("../outside"),
+        title: String::from("Outside"),
+        date: None,
+        text: Some(String::from("Text")),
+    };
+    let err = export_html(&id, vec![note], "", &mut state).unwrap_err();
+
+    assert_eq!(err.kind, TypstErrorKind::InvalidArgument);
+}
+
+#[test]
+fn test_export_escapes_note_title() {
+    let mut state = TypstState::new();
+    let id = state.create_source_id("export", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    let note = ExportNote {
+        path: String::from("note"),
+        title: String::from("\"Ünïcode\" \\ \")#panic()"),
+        date: Some(String::from("2026-10-18")),
+        text: Some(String::from("Text")),
+    };
+    export_html(&id, vec![note], "", &mut state).unwrap();
+
+    let synth = &state.source_context_map[&id].unstable_synth;
+    assert!(synth.contains("#heading(level:1,\"\\\"Ünïcode\\\" \\\\ \\\")#panic()\")\n"));
+    assert!(synth.contains("fill:theme.on-primary-container,\"2026-10-18\"))\n"));
+}
+
+#[test]
+fn test_export_maps_errors_to_their_note() {
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+    let id = state.create_source_id("export", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    // An open note whose editor text differs from the exported text.
+    let open = state.create_source_id("second", String::from("space"));
+    let open_raw = state.get_source_context(&open).raw_id;
+    state.world.insert_source(open_raw, String::from("Editor text\n"));
+
+    let second = "Second\n\nBroken #missing here\n";
+    let notes = vec![
+        ExportNote {
+            path: String::from("first"),
+            title: String::from("First"),
+            date: None,
+            text: Some(String::from("First\n")),
+        },
+        ExportNote {
+            path: String::from("second"),
+            title: String::from("Second"),
+            date: None,
+            text: Some(String::from(second)),
+        },
+    ];
+    let render = export_pdf(&id, notes, "", PdfExportOptions::default(), &mut state).unwrap();
+
+    assert!(render.bytes.is_some());
+
+    let start = second.find("missing").unwrap();
+    let error = render
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.diagnostic.message.contains("missing"))
+        .unwrap();
+    assert_eq!(error.note, Some(1));
+    assert_eq!(error.diagnostic.range, start..start + "missing".len());
+
+    // The open note's raw source is left alone, and the copies are removed.
+    assert_eq!(state.world.get_source(open_raw).unwrap().text(), "Editor text\n");
+    assert_eq!(state.world.files.len(), 3);
+}
@@ -1,13 +1,14 @@
 use std::{hash::BuildHasher, ops::Range};
 
-use rustc_hash::{FxBuildHasher, FxHashSet};
+use rustc_hash::FxBuildHasher;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
-use typst::{compile, diag::Severity};
+use typst::compile;
 use typst_html::{HtmlDocument, HtmlOptions};
 
 use crate::{
-    bindings::{TypstDiagnostic, TypstFileId, map_synth_span},
+    bindings::{TypstDiagnostic, TypstFileId, TypstRecovered},
+    renderer::recovery::{Recovery, recover},
     source::{RenderTarget, SynthResult, sync_source_state},
     state::TypstState,
 };
@@ -18,12 +19,16 @@ pub fn render(
     prelude: &str,
     state: &mut TypstState,
 ) -> HTMLRenderResult {
-    let SynthResult { synth, blocks, .. } =
-        sync_source_state(id, text, prelude, RenderTarget::Html, state);
+    let SynthResult {
+        synth,
+        mut blocks,
+        equation_ranges,
+    } = sync_source_state(id, text, prelude, RenderTarget::Html, state);
 
     let mut last_document = None;
 
     let mut diagnostics = Vec::new();
+    let mut recovered = Vec::new();
     let mut compiled_warnings = None;
 
     // let mut erronous_ranges = Vec::new();
@@ -36,6 +41,7 @@ pub fn render(
         .replace(&synth);
     context.unstable_synth = synth;
 
+    let mut recovery = Recovery::default();
     let mut frames = Vec::new();
 
     while last_document.is_none() {
@@ -61,67 +67,25 @@ pub fn render(
                 }]
             }
             Err(source_diagnostics) => {
-                let error_ranges = source_diagnostics
-                    .iter()
-                    .filter_map(|diagnostic| {
-                        map_synth_span(
-                            diagnostic.span,
-                            diagnostic.severity == Severity::Error,
-                            &diagnostic.trace,
-                            context,
-                            &state.world,
-                        )
-                    })
-                    .collect::<FxHashSet<_>>();
-
-                // crate::log!("[ERROR RANGES]: {error_ranges:?}");
-
-                // let synth_source = context.synth_source(&self.world);
-
-                let Some(block) = blocks.iter().find(|block| {
-                    let raw_range = &block.range;
-
-                    let synth_range_start = context.map_raw_to_synth_from_right(raw_range.start);
-                    let synth_range_end = context.map_raw_to_synth_from_right(raw_range.end);
-                    // let synth_range = synth_range_start..synth_range_end;
-
-                    // crate::log!("[BLOCK RANGE]: {synth_range_start} - {synth_range_end}");
-
-                    error_ranges.iter().any(|error_range| {
-                        (synth_range_start <= error_range.start
-                            && synth_range_end >= error_range.start)
-                            || (synth_range_start <= error_range.end
-                                && synth_range_end >= error_range.end)
-                    })
-                }) else {
-                    break;
-                };
-
-                // let raw_source = context.raw_source(&state.world);
-                // let raw_lines = raw_source.lines();
-
-                let raw_range = &block.range;
-                // let raw_start_utf16 = raw_lines.byte_to_utf16(raw_range.start).unwrap();
-                // let raw_end_utf16 = raw_lines.byte_to_utf16(raw_range.end).unwrap();
-                // let raw_range_utf16 = raw_start_utf16..raw_end_utf16;
-
-                let mut end_byte = context.map_raw_to_synth_from_right(raw_range.end);
-                if block.is_inline {
-                    end_byte += 12;
-                }
-
                 diagnostics.extend(TypstDiagnostic::from_diagnostics(
-                    source_diagnostics,
+                    source_diagnostics.clone(),
                     context,
                     &state.world,
                 ));
 
                 crate::error!("[ERRORS]: {diagnostics:?}");
 
-                let start_byte = context.map_raw_to_synth_from_right(raw_range.start);
-
-                let source = context.synth_source_mut(&mut state.world).unwrap();
-                source.edit(start_byte..end_byte, &(" ".repeat(end_byte - start_byte)));
+                let Some(edits) = recover(
+                    &mut recovery,
+                    &source_diagnostics,
+                    &mut blocks,
+                    &equation_ranges,
+                    context,
+                    &mut state.world,
+                ) else {
+                    break;
+                };
+                recovered.extend(edits);
 
                 Vec::new()
             }
@@ -141,6 +105,7 @@ pub fn render(
     HTMLRenderResult {
         frames,
         diagnostics,
+        recovered,
     }
 }
 
@@ -149,6 +114,7 @@ pub fn render(
 pub struct HTMLRenderResult {
     pub frames: Vec<HTMLRangedFrame>,
     pub diagnostics: Vec<TypstDiagnostic>,
+    pub recovered: Vec<TypstRecovered>,
 }
 
 #[derive(Debug, Clone, Tsify, Serialize, Deserialize)]
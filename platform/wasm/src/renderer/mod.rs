@@ -1,3 +1,4 @@
+pub mod export;
 pub mod html;
 pub mod paged;
 pub mod recovery;
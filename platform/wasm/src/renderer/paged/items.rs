@@ -1,4 +1,9 @@
-use std::{cmp, collections::VecDeque, iter, ops::Range};
+use std::{
+    cmp,
+    collections::{BTreeMap, VecDeque},
+    iter, mem,
+    ops::Range,
+};
 
 use typst::{
     WorldExt, compile,
@@ -12,7 +17,7 @@ use crate::{
     bindings::{TypstDiagnostic, TypstFileId},
     renderer::{
         paged::{BoundFrameItem, FrameItemsChunk, PagedRender},
-        recovery::{map_error_mark_index, remove_errornous_block, try_mark_errornous},
+        recovery::{Recovery, recover},
     },
     source::{AstBlock, RenderTarget, SourceContext, SynthResult, sync_source_context},
     state::TypstState,
@@ -20,7 +25,7 @@ use crate::{
 };
 
 /// Chunks a Typst document into renderable blocks by frame items, handling
-/// diagnostics and error divergence.
+/// diagnostics and error recovery.
 #[typst_macros::time]
 pub fn chunk_by_items(
     id: &TypstFileId,
@@ -43,29 +48,24 @@ pub fn chunk_by_items(
         .replace(&synth);
     context.unstable_synth = synth;
 
-    let mut divergence = 0_u8;
-
-    chunk_by_items_with_blocks(
-        &mut blocks,
-        &equation_ranges,
-        &mut divergence,
-        context,
-        &mut state.world,
-    )
+    chunk_by_items_with_blocks(&mut blocks, &equation_ranges, context, &mut state.world)
 }
 
+/// Compiles the synth and chunks its frame items by block, recovering from
+/// errors until it compiles or recovery gives up.
 #[allow(clippy::iter_with_drain)]
 #[typst_macros::time]
 pub fn chunk_by_items_with_blocks(
     blocks: &mut Vec<AstBlock>,
-    eq_ranges: &Vec<Range<usize>>,
-    divergence: &mut u8,
+    eq_ranges: &[Range<usize>],
     context: &mut SourceContext,
     world: &mut MnemoWorld,
 ) -> PagedRender {
+    let mut recovery = Recovery::default();
     let mut document = None;
 
     let mut diagnostics = Vec::new();
+    let mut recovered = Vec::new();
     let mut compiled_warnings = None;
 
     let mut chunks = Vec::new();
@@ -82,19 +82,57 @@ pub fn chunk_by_items_with_blocks(
                 let mut sink = BoundFrameSink::default();
                 let mut bound_frame_items = Vec::new();
 
-                for page in document.pages() {
+                for (idx, page) in document.pages().iter().enumerate() {
+                    sink.page = idx;
+
                     for frame_item in page.frame.items() {
                         let frame_block = bound_frame(frame_item, None, &mut sink, context, world);
                         bound_frame_items.extend(frame_block);
                     }
                 }
 
-                let mut bound_frame_items = bound_frame_items.into_iter().peekable();
+                // Synth offset where each block ends.
+                let block_ends = blocks
+                    .iter()
+                    .map(|block| context.map_raw_to_synth_from_right(block.range.end))
+                    .collect::<Vec<_>>();
+
+                // Items go to the block their range ends in, so a float that
+                // lands on a later page still joins its own block. Items
+                // without a range go with the next ranged item.
+                let mut block_items = iter::repeat_with(VecDeque::<BoundFrameItem>::new)
+                    .take(blocks.len())
+                    .collect::<Vec<_>>();
+                let mut unranged_items = Vec::<BoundFrameItem>::new();
+
+                for frame_block in bound_frame_items {
+                    let Some(range) = &frame_block.range else {
+                        unranged_items.push(frame_block);
+                        continue;
+                    };
+
+                    let idx = block_ends.partition_point(|&end| end < range.end);
+                    if let Some(items) = block_items.get_mut(idx) {
+                        items.extend(unranged_items.drain(..));
+                        items.push_back(frame_block);
+                    }
+                }
+
+                // Offset of each page's top from the top of the document.
+                let page_offsets = document
+                    .pages()
+                    .iter()
+                    .scan(0_f64, |offset, page| {
+                        let page_offset = *offset;
+                        *offset += page.frame.height().to_pt();
+                        Some(page_offset)
+                    })
+                    .collect::<Vec<_>>();
 
                 let mut chunks = Vec::with_capacity(blocks.len());
                 let mut remaining_items = Vec::<BoundFrameItem>::new();
 
-                for block in blocks.iter() {
+                for (idx, (block, chunk_items)) in blocks.iter().zip(block_items).enumerate() {
                     let raw_source = context.raw_source(world).unwrap();
 
                     let raw_range = &block.range;
@@ -103,60 +141,22 @@ pub fn chunk_by_items_with_blocks(
                     let raw_end_utf16 = raw_lines.byte_to_utf16(raw_range.end).unwrap();
                     let raw_range_utf16 = raw_start_utf16..raw_end_utf16;
 
-                    // let synth_range_start = context.map_raw_to_synth_from_left(raw_range.start);
-                    let synth_range_end = context.map_raw_to_synth_from_right(raw_range.end);
-                    // let synth_range = synth_range_start..synth_range_end;
-
-                    let mut chunk_items = VecDeque::<BoundFrameItem>::new();
-                    let mut deferred_items = Vec::<BoundFrameItem>::new();
-
-                    let mut block_start_width = None;
-                    let mut block_start_height = None;
-                    let mut block_end_width = None;
-                    let mut block_end_height = None;
-
-                    while let Some(frame_block) = bound_frame_items.peek() {
-                        if let Some(range) = &frame_block.range {
-                            if range.end <= synth_range_end {
-                                let frame_block = bound_frame_items.next().unwrap();
-
-                                // crate::log!("{frame_block:#?}");
-
-                                match block_start_width {
-                                    Some(width) if width < frame_block.bounds.min.x => {}
-                                    _ => block_start_width = Some(frame_block.bounds.min.x),
-                                }
-
-                                match block_start_height {
-                                    Some(height) if height < frame_block.bounds.min.y => {}
-                                    _ => block_start_height = Some(frame_block.bounds.min.y),
-                                }
-
-                                match block_end_width {
-                                    Some(width) if width > frame_block.bounds.max.x => {}
-                                    _ => block_end_width = Some(frame_block.bounds.max.x),
-                                }
-
-                                match block_end_height {
-                                    Some(height) if height > frame_block.bounds.max.y => {}
-                                    _ => block_end_height = Some(frame_block.bounds.max.y),
-                                }
-
-                                chunk_items.extend(deferred_items.drain(..));
-                                chunk_items.push_back(frame_block);
-                            } else {
-                                break;
-                            }
-                        } else {
-                            let frame_block = bound_frame_items.next().unwrap();
-                            deferred_items.push(frame_block);
-                        }
-                    }
+                    // Items without a range after the last ranged one.
+                    let mut deferred_items = if idx + 1 == blocks.len() {
+                        mem::take(&mut unranged_items)
+                    } else {
+                        Vec::new()
+                    };
 
-                    let block_start_width = block_start_width.unwrap_or_default().to_pt();
-                    let block_start_height = block_start_height.unwrap_or_default().to_pt();
-                    let block_end_width = block_end_width.unwrap_or_default().to_pt();
-                    let block_end_height = block_end_height.unwrap_or_default().to_pt();
+                    // A block broken across pages becomes one chunk per page.
+                    let mut page_items = split_by_page(chunk_items);
+
+                    let block_start_height = page_items
+                        .first()
+                        .and_then(ranged_bounds)
+                        .map_or(0_f64, |(page, bounds)| {
+                            page_offsets[page] + bounds.min.y.to_pt()
+                        });
 
                     match context.height {
                         Some(height) if block_start_height >= height => {
@@ -166,34 +166,46 @@ pub fn chunk_by_items_with_blocks(
                     }
 
                     if block.is_inline {
+                        if page_items.is_empty() {
+                            page_items.push(VecDeque::new());
+                        }
+                        let first_items = &mut page_items[0];
+
                         let length = remaining_items.len();
-                        chunk_items.reserve(length.saturating_add(1));
+                        first_items.reserve(length.saturating_add(1));
 
                         for remaining in remaining_items.drain(..).rev() {
-                            chunk_items.push_front(remaining);
+                            first_items.push_front(remaining);
                         }
                     }
 
                     remaining_items.append(&mut deferred_items);
 
-                    // crate::log!("start width: {block_start_width}");
-                    // crate::log!("end width: {block_end_width}");
+                    for items in page_items {
+                        let Some((page, bounds)) = ranged_bounds(&items) else {
+                            continue;
+                        };
 
-                    let block_width = block_end_width - block_start_width;
-                    let block_height = block_end_height - block_start_height;
+                        // crate::log!("start width: {}", bounds.min.x.to_pt());
+                        // crate::log!("end width: {}", bounds.max.x.to_pt());
 
-                    if block_width <= 0_f64 || block_height <= 0_f64 {
-                        continue;
-                    }
+                        let block_width = (bounds.max.x - bounds.min.x).to_pt();
+                        let block_height = (bounds.max.y - bounds.min.y).to_pt();
 
-                    chunks.push(FrameItemsChunk {
-                        items: chunk_items,
-                        range: raw_range_utf16,
-                        width: block_width,
-                        height: block_height,
-                        x_offset: block_start_width,
-                        y_offset: block_start_height,
-                    });
+                        if block_width <= 0_f64 || block_height <= 0_f64 {
+                            continue;
+                        }
+
+                        chunks.push(FrameItemsChunk {
+                            items,
+                            range: raw_range_utf16.clone(),
+                            page,
+                            width: block_width,
+                            height: block_height,
+                            x_offset: bounds.min.x.to_pt(),
+                            y_offset: bounds.min.y.to_pt(),
+                        });
+                    }
                 }
 
                 if !remaining_items.is_empty()
@@ -207,16 +219,15 @@ pub fn chunk_by_items_with_blocks(
                     }
                 }
 
+                // Chunks are built in source order, but a float can place a
+                // block on a later page than the blocks after it.
+                chunks.sort_by(|a, b| {
+                    a.page.cmp(&b.page).then(a.y_offset.total_cmp(&b.y_offset))
+                });
+
                 (chunks, sink.tooltips, Some(document))
             }
             Err(source_diagnostics) => {
-                *divergence += 1;
-                if *divergence >= 5 {
-                    crate::error!("COULD NOT CONVERGE AFTER 5 ITERATIONS ‼️");
-
-                    break;
-                }
-
                 diagnostics.extend(TypstDiagnostic::from_diagnostics(
                     source_diagnostics.clone(),
                     context,
@@ -225,58 +236,17 @@ pub fn chunk_by_items_with_blocks(
 
                 crate::error!("[ERRORS]: {diagnostics:?}");
 
-                let marked_errors =
-                    try_mark_errornous(&source_diagnostics, eq_ranges, context, world);
-
-                if !marked_errors.marks.is_empty() {
-                    let index_mapper = context.index_mapper.clone();
-                    map_error_mark_index(&marked_errors, context);
-
-                    let marked_render =
-                        chunk_by_items_with_blocks(blocks, eq_ranges, divergence, context, world);
-
-                    let synth_source = context.synth_source_mut(world).unwrap();
-
-                    for mark in &marked_errors.marks {
-                        let start_byte = mark.synth_range.start;
-                        let end_byte = mark.synth_range.end;
-
-                        // fill with placeholder to stablize ranges
-                        let byte_length = end_byte - start_byte;
-                        let placeholder = format!("{:>byte_length$}", "\"\"");
-                        synth_source.edit(start_byte..end_byte, &placeholder);
-                    }
-
-                    let stable_render =
-                        chunk_by_items_with_blocks(blocks, eq_ranges, divergence, context, world);
-
-                    let synth_source = context.synth_source_mut(world).unwrap();
-
-                    for mark in marked_errors.marks {
-                        let start_byte = mark.synth_range.start;
-                        synth_source.edit(start_byte..(start_byte + mark.text.len()), &mark.text);
-                    }
-
-                    context.index_mapper = index_mapper;
-
-                    return PagedRender {
-                        chunks: marked_render.chunks,
-                        tooltips: marked_render.tooltips,
-                        diagnostics,
-                        document: stable_render.document,
-                    };
-                }
-
-                let indicies = remove_errornous_block(blocks, &source_diagnostics, context, world);
-
-                if indicies.is_empty() {
-                    crate::error!("NO ERROR BLOCKS FOUND ‼️");
+                let Some(edits) = recover(
+                    &mut recovery,
+                    &source_diagnostics,
+                    blocks,
+                    eq_ranges,
+                    context,
+                    world,
+                ) else {
                     break;
-                }
-
-                for idx in indicies.iter().rev() {
-                    blocks.remove(*idx);
-                }
+                };
+                recovered.extend(edits);
 
                 (Vec::new(), Vec::new(), None)
             }
@@ -358,9 +328,12 @@ pub fn chunk_by_items_with_blocks(
 
             // crate::log!("raw_range_utf16: {:?}", raw_start_utf16..raw_end_utf16);
 
+            let page = items.first().map_or(0, |item| item.page);
+
             Some(FrameItemsChunk {
                 items: VecDeque::from(items),
                 range: raw_range_utf16,
+                page,
                 width: block_end_width - block_start_width,
                 height: block_end_height - block_start_height,
                 x_offset: block_start_width,
@@ -375,10 +348,54 @@ pub fn chunk_by_items_with_blocks(
         chunks,
         tooltips,
         diagnostics,
+        recovered,
         document,
     }
 }
 
+/// Groups a block's items by page, in page order. Items keep their order
+/// within a page, and unranged items go with the page of the ranged item
+/// before them, or of the first ranged item if none precedes them.
+fn split_by_page(items: VecDeque<BoundFrameItem>) -> Vec<VecDeque<BoundFrameItem>> {
+    let first_page = items
+        .iter()
+        .find(|item| item.range.is_some())
+        .map(|item| item.page);
+
+    let mut pages = BTreeMap::<usize, VecDeque<BoundFrameItem>>::new();
+    let mut page = first_page;
+
+    for item in items {
+        if item.range.is_some() {
+            page = Some(item.page);
+        }
+
+        pages
+            .entry(page.unwrap_or(item.page))
+            .or_default()
+            .push_back(item);
+    }
+
+    pages.into_values().collect()
+}
+
+/// The page and bounding box of the ranged items in a run.
+fn ranged_bounds(items: &VecDeque<BoundFrameItem>) -> Option<(usize, Rect)> {
+    let mut ranged = items.iter().filter(|item| item.range.is_some());
+    let first = ranged.next()?;
+
+    let bounds = ranged.fold(first.bounds, |mut bounds, item| {
+        bounds.min.x = cmp::min(bounds.min.x, item.bounds.min.x);
+        bounds.min.y = cmp::min(bounds.min.y, item.bounds.min.y);
+        bounds.max.x = cmp::max(bounds.max.x, item.bounds.max.x);
+        bounds.max.y = cmp::max(bounds.max.y, item.bounds.max.y);
+
+        bounds
+    });
+
+    Some((first.page, bounds))
+}
+
 /// Recursively bounds a frame item, producing frame blocks with position and
 /// range.
 // #[comemo::memoize]
@@ -454,6 +471,7 @@ fn bound_frame(
 
             let mut item = BoundFrameItem {
                 range,
+                page: sink.page,
                 bounds,
                 item: item.clone(),
                 point: *point,
@@ -489,6 +507,7 @@ fn bound_frame(
 
     let mut item = BoundFrameItem {
         range,
+        page: sink.page,
         bounds,
         item: item.clone(),
         point: *point,
@@ -510,6 +529,8 @@ fn bound_frame(
 
 #[derive(Default)]
 struct BoundFrameSink {
+    /// Index of the page whose items are being bounded.
+    page: usize,
     tooltips: Vec<Vec<BoundFrameItem>>,
     tag_stack: Vec<(&'static str, Span)>,
 }
@@ -614,3 +635,97 @@ fn frame_item_range(
         None
     }
 }
+
+#[test]
+fn test_page_mode_height_counts_earlier_pages() {
+    use crate::renderer::paged::pdf::PdfExportOptions;
+
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+    state.set_page_mode(
+        &id,
+        Some(PdfExportOptions {
+            paper: Some(String::from("a6")),
+            ..PdfExportOptions::default()
+        }),
+    );
+
+    // The box leaves no room for "Second" on the first page.
+    let text = "First\n\n#box(height: 330pt)\n\nSecond\n";
+    let second = text.find("Second").unwrap();
+    let renders_second = |state: &mut TypstState| {
+        let result = state.compile_paged(&id, text, "");
+        assert!(!result.frames.is_empty());
+
+        result.frames.iter().any(|frame| frame.range.start >= second)
+    };
+
+    assert!(renders_second(&mut state));
+
+    // Shorter than the first page, though "Second" sits near the top of its
+    // own page.
+    state.resize(&id, None, Some(200.0));
+    assert!(!renders_second(&mut state));
+}
+
+#[test]
+fn test_page_mode_orders_frames_around_floats() {
+    use typst::layout::Abs;
+
+    use crate::renderer::paged::{FrameBounds, pdf::PdfExportOptions};
+
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+    state.set_page_mode(
+        &id,
+        Some(PdfExportOptions {
+            paper: Some(String::from("a6")),
+            ..PdfExportOptions::default()
+        }),
+    );
+
+    // Contextual blocks are not wrapped in a block, so the figure floats in the
+    // page's flow. It does not fit under the box, so it moves to the second
+    // page while "After" stays on the first.
+    let text = "First\n\n#box(height: 200pt)\n\n\
+        #context figure(placement: auto, rect(height: 300pt))\n\nAfter\n";
+    let result = state.compile_paged(&id, text, "");
+
+    let after = text.find("After").unwrap();
+    let figure = text.find("#context").unwrap();
+    let page_of = |start: usize| {
+        result
+            .frames
+            .iter()
+            .find(|frame| frame.range.start == start)
+            .unwrap()
+            .render
+            .page
+    };
+    assert_eq!(page_of(after), 0);
+    assert_eq!(page_of(figure), 1);
+
+    for (idx, page) in result.pages.iter().enumerate() {
+        assert!(result.frames[page.frames.clone()].iter().all(|frame| frame.render.page == idx));
+    }
+    assert_eq!(result.pages.last().unwrap().frames.end, result.frames.len());
+
+    let bounds = &state.get_source_context(&id).frame_bounds;
+    let (index, _, _) =
+        FrameBounds::locate(bounds, 1, Point::new(Abs::pt(150.0), Abs::pt(100.0))).unwrap();
+    assert_eq!(result.frames[index].range.start, figure);
+}
@@ -1,5 +1,6 @@
 // pub mod blocks;
 pub mod items;
+pub mod pdf;
 pub mod svg;
 
 use std::{collections::VecDeque, hash::Hash, ops::Range};
@@ -7,7 +8,7 @@ use std::{collections::VecDeque, hash::Hash, ops::Range};
 use typst::layout::{FrameItem, Point, Rect};
 use typst_layout::PagedDocument;
 
-use crate::bindings::TypstDiagnostic;
+use crate::bindings::{TypstDiagnostic, TypstRecovered};
 
 /// Result of paged rendering, containing chunks, diagnostics, and document context.
 #[derive(Debug)]
@@ -18,6 +19,8 @@ pub struct PagedRender {
     pub tooltips: Vec<FrameItemsChunk>,
     /// Diagnostics and warnings produced during rendering.
     pub diagnostics: Vec<TypstDiagnostic>,
+    /// Parts of the note that recovery neutralized.
+    pub recovered: Vec<TypstRecovered>,
     /// The paged Typst document, if available.
     pub document: Option<PagedDocument>,
 }
@@ -29,6 +32,8 @@ pub struct FrameItemsChunk {
     pub items: VecDeque<BoundFrameItem>,
     /// UTF-16 range in the source corresponding to this chunk.
     pub range: Range<usize>,
+    /// Index of the page this chunk is on.
+    pub page: usize,
     /// Width of the chunk in points.
     pub width: f64,
     /// Height of the chunk in points.
@@ -39,11 +44,74 @@ pub struct FrameItemsChunk {
     pub y_offset: f64,
 }
 
+/// Where a rendered frame sits on the page.
+///
+/// Kept on the [`SourceContext`](crate::source::SourceContext) after each
+/// paged compile, so document positions can be mapped back to frames.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameBounds {
+    /// Index of the page the frame is on.
+    pub page: usize,
+    /// Offset from the left of the page in points.
+    pub x_offset: f64,
+    /// Offset from the top of the page in points.
+    pub y_offset: f64,
+    /// Width of the frame in points.
+    pub width: f64,
+    /// Height of the frame in points.
+    pub height: f64,
+}
+
+impl FrameBounds {
+    /// Finds the frame containing the page-relative `point` on `page`,
+    /// returning its index and the point relative to the frame's top-left
+    /// corner.
+    ///
+    /// `bounds` must be sorted by page and then by offset from the top. Points
+    /// between frames resolve to the closest frame above them on the same
+    /// page.
+    #[must_use]
+    pub fn locate(bounds: &[Self], page: usize, point: Point) -> Option<(usize, f64, f64)> {
+        let x = point.x.to_pt();
+        let y = point.y.to_pt();
+
+        let start = bounds.partition_point(|bounds| bounds.page < page);
+        let end = bounds.partition_point(|bounds| bounds.page <= page);
+        let page_bounds = &bounds[start..end];
+
+        let index = page_bounds
+            .partition_point(|bounds| bounds.y_offset <= y)
+            .checked_sub(1)
+            .or_else(|| (!page_bounds.is_empty()).then_some(0))?;
+        let frame = &page_bounds[index];
+
+        Some((
+            start + index,
+            (x - frame.x_offset).max(0.0),
+            (y - frame.y_offset).max(0.0),
+        ))
+    }
+}
+
+impl From<&FrameItemsChunk> for FrameBounds {
+    fn from(chunk: &FrameItemsChunk) -> Self {
+        Self {
+            page: chunk.page,
+            x_offset: chunk.x_offset,
+            y_offset: chunk.y_offset,
+            width: chunk.width,
+            height: chunk.height,
+        }
+    }
+}
+
 /// A single frame item with bounds and range.
 #[derive(Debug, Clone)]
 pub struct BoundFrameItem {
     /// Optional byte range in the source for this block.
     pub range: Option<Range<usize>>,
+    /// Index of the page the block is on.
+    pub page: usize,
     /// Bounding box of the block.
     pub bounds: Rect,
     /// The frame item to render.
@@ -61,3 +129,30 @@ impl Hash for BoundFrameItem {
         self.point.hash(state);
     }
 }
+
+#[test]
+fn test_locate_frame_on_second_page() {
+    let frame = |page, x_offset, y_offset| {
+        FrameBounds {
+            page,
+            x_offset,
+            y_offset,
+            width: 100.0,
+            height: 20.0,
+        }
+    };
+    let bounds = [
+        frame(0, 10.0, 10.0),
+        frame(0, 10.0, 40.0),
+        frame(1, 10.0, 10.0),
+        frame(1, 30.0, 40.0),
+    ];
+
+    let point = |x, y| Point::new(typst::layout::Abs::pt(x), typst::layout::Abs::pt(y));
+
+    assert_eq!(FrameBounds::locate(&bounds, 1, point(35.0, 45.0)), Some((3, 5.0, 5.0)));
+    assert_eq!(FrameBounds::locate(&bounds, 1, point(15.0, 35.0)), Some((2, 5.0, 25.0)));
+    // Above the first frame of the page.
+    assert_eq!(FrameBounds::locate(&bounds, 1, point(0.0, 0.0)), Some((2, 0.0, 0.0)));
+    assert_eq!(FrameBounds::locate(&bounds, 2, point(0.0, 0.0)), None);
+}
@@ -0,0 +1,151 @@
+use std::{fmt, mem};
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use typst_pdf::PdfOptions;
+
+use crate::{
+    bindings::{TypstDiagnostic, TypstFileId},
+    renderer::paged::{PagedRender, items::chunk_by_items},
+    source::RenderTarget,
+    state::TypstState,
+};
+
+/// Renders a note to a PDF document, using the same error recovery as the
+/// SVG renderer.
+///
+/// `options` only apply to this render; the note's page mode preview keeps
+/// its own page setup.
+#[typst_macros::time]
+pub fn render_pdf(
+    id: &TypstFileId,
+    text: &str,
+    prelude: &str,
+    options: PdfExportOptions,
+    state: &mut TypstState,
+) -> PdfRender {
+    let preview_options =
+        mem::replace(&mut state.get_source_context_mut(id).pdf_options, options);
+
+    let PagedRender {
+        mut diagnostics,
+        document,
+        ..
+    } = chunk_by_items(id, text, prelude, RenderTarget::Pdf, state);
+
+    state.get_source_context_mut(id).pdf_options = preview_options;
+
+    let context = state.get_source_context(id);
+
+    let bytes = document.and_then(|document| {
+        match typst_pdf::pdf(&document, &PdfOptions::default()) {
+            Ok(bytes) => Some(bytes),
+            Err(source_diagnostics) => {
+                diagnostics.extend(TypstDiagnostic::from_diagnostics(
+                    source_diagnostics,
+                    context,
+                    &state.world,
+                ));
+
+                None
+            }
+        }
+    });
+
+    PdfRender { bytes, diagnostics }
+}
+
+/// Result of PDF rendering, containing the encoded document and diagnostics.
+#[derive(Debug)]
+pub struct PdfRender {
+    /// The encoded PDF, if compilation and export succeeded.
+    pub bytes: Option<Vec<u8>>,
+    /// Diagnostics and warnings produced during rendering.
+    pub diagnostics: Vec<TypstDiagnostic>,
+}
+
+/// Page setup for PDF export.
+#[derive(Debug, Clone, Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PdfExportOptions {
+    /// A Typst paper name (e.g. `"a4"` or `"us-letter"`). When unset, the page
+    /// keeps the note's width and grows to fit its content.
+    pub paper: Option<String>,
+    /// Page margins in points.
+    pub margin: PdfMargin,
+    /// Whether to paint the page with the theme's background color.
+    pub fill: bool,
+}
+
+impl Default for PdfExportOptions {
+    fn default() -> Self {
+        Self {
+            paper: None,
+            margin: PdfMargin::default(),
+            fill: true,
+        }
+    }
+}
+
+/// Page margins in points.
+#[derive(Debug, Clone, Copy, Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PdfMargin {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Default for PdfMargin {
+    fn default() -> Self {
+        Self {
+            top: 16.0,
+            right: 16.0,
+            bottom: 16.0,
+            left: 16.0,
+        }
+    }
+}
+
+impl fmt::Display for PdfMargin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(top:{}pt,right:{}pt,bottom:{}pt,left:{}pt)",
+            self.top, self.right, self.bottom, self.left
+        )
+    }
+}
+
+#[test]
+fn test_render_pdf_recovers_errors() {
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+    state.set_page_mode(&id, Some(PdfExportOptions::default()));
+
+    let text = "Before\n\n#missing\n\nAfter\n";
+    let options = PdfExportOptions {
+        paper: Some(String::from("a5")),
+        ..PdfExportOptions::default()
+    };
+    let PdfRender { bytes, diagnostics } = render_pdf(&id, text, "", options, &mut state);
+
+    assert!(bytes.unwrap().starts_with(b"%PDF"));
+
+    let start = text.find("missing").unwrap();
+    let error = diagnostics.iter().find(|diagnostic| diagnostic.message.contains("missing"));
+    assert_eq!(error.unwrap().range, start..start + "missing".len());
+
+    // The export's paper does not leak into the page mode preview.
+    assert!(state.get_source_context(&id).pdf_options.paper.is_none());
+    assert!(state.prelude(&id, RenderTarget::Pdf).contains("width:auto"));
+}
@@ -10,9 +10,9 @@ use typst::{
 };
 use typst_svg::svg_in_html;
 
-use super::BoundFrameItem;
+use super::{BoundFrameItem, FrameBounds};
 use crate::{
-    bindings::{TypstDiagnostic, TypstFileId},
+    bindings::{TypstDiagnostic, TypstFileId, TypstRecovered},
     renderer::paged::{PagedRender, items::chunk_by_items},
     source::RenderTarget,
     state::TypstState,
@@ -30,10 +30,33 @@ pub fn render_svgs_by_items(
         chunks,
         tooltips,
         diagnostics,
+        recovered,
         document,
     } = chunk_by_items(id, text, prelude, RenderTarget::Svg, state);
 
     let context = state.get_source_context_mut(id);
+    context.frame_bounds = chunks.iter().map(FrameBounds::from).collect();
+
+    let pages = document
+        .as_ref()
+        .map(|document| {
+            document
+                .pages()
+                .iter()
+                .enumerate()
+                .map(|(idx, page)| {
+                    let start = context.frame_bounds.partition_point(|bounds| bounds.page < idx);
+                    let end = context.frame_bounds.partition_point(|bounds| bounds.page <= idx);
+
+                    SvgPage {
+                        width: page.frame.width().to_pt(),
+                        height: page.frame.height().to_pt(),
+                        frames: start..end,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
 
     let (frames, tooltips) = if let Some(document) = &document {
         let link_resolver = LateLinkResolver::new(None, document.introspector().as_ref());
@@ -59,6 +82,7 @@ pub fn render_svgs_by_items(
                 render_svg(
                     items,
                     chunk.range,
+                    chunk.page,
                     width,
                     height,
                     x_offset,
@@ -82,6 +106,7 @@ pub fn render_svgs_by_items(
                 render_svg(
                     items,
                     chunk.range,
+                    chunk.page,
                     width,
                     height,
                     x_offset,
@@ -102,7 +127,9 @@ pub fn render_svgs_by_items(
     SvgRender {
         frames,
         tooltips,
+        pages,
         diagnostics,
+        recovered,
     }
 }
 
@@ -113,6 +140,7 @@ pub fn render_svgs_by_items(
 fn render_svg(
     items: Prehashed<VecDeque<BoundFrameItem>>,
     range: Range<usize>,
+    page: usize,
     width: Abs,
     height: Abs,
     x_offset: Abs,
@@ -139,6 +167,7 @@ fn render_svg(
 
     let render = SvgFrameRender {
         svg,
+        page,
         width,
         height,
         x_offset,
@@ -157,8 +186,24 @@ pub struct SvgRender {
     pub frames: Vec<SvgRangedFrame>,
     /// Rendered SVG frames for tooltips.
     pub tooltips: Vec<SvgRangedFrame>,
+    /// The document's pages and the frames on each.
+    pub pages: Vec<SvgPage>,
     /// Diagnostics and warnings produced during rendering.
     pub diagnostics: Vec<TypstDiagnostic>,
+    /// Parts of the note that recovery neutralized.
+    pub recovered: Vec<TypstRecovered>,
+}
+
+/// A page of the rendered document.
+#[derive(Debug, Clone, Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SvgPage {
+    /// Width of the page in points.
+    pub width: f64,
+    /// Height of the page in points.
+    pub height: f64,
+    /// Indices of the frames on this page, in rendering order.
+    pub frames: Range<usize>,
 }
 
 /// An SVG frame with its corresponding source range.
@@ -184,6 +229,8 @@ impl SvgRangedFrame {
 pub struct SvgFrameRender {
     /// SVG markup as a string.
     pub svg: String,
+    /// Index of the page the frame is on.
+    pub page: usize,
     /// Width of the frame in points.
     pub width: f64,
     /// Height of the frame in points.
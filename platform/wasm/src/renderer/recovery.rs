@@ -1,239 +1,804 @@
 //! Error recovery for failed synth compiles.
 //!
-//! When a compile fails, the recovery pass identifies which part of the synth
-//! is responsible, neutralizes it, and returns enough information for the
-//! caller to retry. Two strategies are available, applied in order
-//! of specificity:
+//! When a compile fails, recovery finds which part of the synth is
+//! responsible, neutralizes it, and lets the caller retry. Every render target
+//! drives the same [`Recovery`] engine, so they recover from the same errors
+//! the same way.
 //!
-//! ## Block removal
+//! ## Strategies
 //!
-//! [`remove_errornous_block`] operates at the granularity of the blocks
-//! produced by `sync_source_context`. It finds the block whose synth range
-//! overlaps the diagnostic span and overwrites it with whitespace.
+//! The engine asks a list of [`RecoveryStrategy`]s, most specific first, for
+//! edits that neutralize the errors of an attempt. The first strategy that
+//! proposes an edit not tried before wins the attempt.
 //!
-//! Crucially, the replacement is exactly the same byte length as the block it
-//! replaces. This keeps every other anchor in the `IndexMapper` valid without
-//! needing to recompute them.
+//! - [`MathMarking`] wraps a broken math expression in a red-text marker, so
+//!   the rest of the equation keeps rendering. The marker changes the synth's
+//!   length, so [`map_error_mark_index`] records it in the `IndexMapper`.
+//! - [`ExpressionMarking`] replaces the innermost failing expression in
+//!   markup or code, a function call, field access or variable, with a
+//!   red-text marker showing its source text, so the rest of the paragraph
+//!   keeps rendering. It too is recorded by [`map_error_mark_index`].
+//! - [`BlockBlanking`] overwrites the blocks produced by `sync_source_context`
+//!   that contain an error with whitespace of exactly the same length. Every
+//!   other anchor in the `IndexMapper` stays valid.
 //!
-//! ## Math marking
+//! Strategies only see the synth text and ranges in it, so they and the engine
+//! can be tested on plain strings with [`apply_to_string`].
 //!
-//! [`try_mark_errornous`] operates at the granularity of individual math
-//! expressions. It is tried first, before block removal, when the diagnostic
-//! falls inside an equation range. Rather than blanking the broken expression,
-//! it wraps it in a red-text marker so the rest of the equation keeps
-//! rendering normally.
+//! ## Convergence
 //!
-//! Because this wrapper changes the synth's byte length, `try_mark_errornous`
-//! returns a [`MarkedResult`] that includes the length delta, and the caller
-//! is responsible for folding that delta back into the `IndexMapper`.
-//!
-//! ## Recovery loop
-//!
-//! The caller runs these passes in a bounded retry loop (see
-//! `renderer/paged/items.rs` for the SVG case). The loop terminates when either
-//! a compile succeeds or the divergence counter reaches its limit, preventing
-//! infinite loops on documents that genuinely cannot be fixed by blanking.
+//! Recovery gives up after [`MAX_ATTEMPTS`] attempts, or as soon as no
+//! strategy has anything new to propose. An edit is not new when it marks an
+//! error inside an earlier marker of either kind, or blanks a range that is
+//! already blank, as retrying those cannot get any further.
 
-use std::ops::Range;
+use std::{iter, ops::Range};
 
-use ecow::EcoVec;
-use rustc_hash::FxHashSet;
-use typst::diag::{Severity, SourceDiagnostic};
+use typst::{
+    diag::{Severity, SourceDiagnostic},
+    ecow::eco_vec,
+};
+use typst_syntax::{LinkedNode, SyntaxKind};
 
 use crate::{
-    bindings::map_synth_span,
+    bindings::{TypstDiagnostic, TypstRecovered, map_synth_span},
     source::{AstBlock, SourceContext},
     world::MnemoWorld,
 };
 
-/// Removes the block containing the first error from the source, updating
-/// diagnostics and context.
+/// The most compile attempts recovery makes before giving up.
+pub const MAX_ATTEMPTS: usize = 16;
+
+/// Synth ranges the strategies can act on.
+#[derive(Debug, Default, Clone)]
+pub struct RecoveryRegions {
+    /// Synth ranges of the note's top-level blocks.
+    pub blocks: Vec<Range<usize>>,
+    /// Synth ranges of the note's equations.
+    pub equations: Vec<Range<usize>>,
+}
+
+impl RecoveryRegions {
+    /// Maps the raw ranges of a note's blocks and equations into the synth.
+    #[must_use]
+    pub fn new(blocks: &[AstBlock], equations: &[Range<usize>], context: &SourceContext) -> Self {
+        let to_synth = |range: &Range<usize>| {
+            context.map_raw_to_synth_from_left(range.start)
+                ..context.map_raw_to_synth_from_right(range.end)
+        };
+
+        Self {
+            blocks: blocks.iter().map(|block| to_synth(&block.range)).collect(),
+            equations: equations.iter().map(to_synth).collect(),
+        }
+    }
+}
+
+/// A change to the synth that neutralizes an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryEdit {
+    pub kind: RecoveryEditKind,
+    /// The synth range the edit applies to.
+    pub range: Range<usize>,
+    /// Index of the error, among those given to the strategy, that the edit
+    /// neutralizes.
+    pub error: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryEditKind {
+    /// Inserts text around the range, shifting everything after it.
+    Wrap {
+        prefix: &'static str,
+        suffix: &'static str,
+    },
+    /// Replaces the range with text at least as long.
+    Replace { text: String },
+    /// Overwrites the range with whitespace of the same length.
+    Blank,
+}
+
+impl RecoveryEdit {
+    /// The range the edit covers once applied.
+    #[must_use]
+    pub fn applied_range(&self) -> Range<usize> {
+        self.range.start..(self.range.end + self.delta())
+    }
+
+    /// How many bytes the edit adds to the synth.
+    #[must_use]
+    pub fn delta(&self) -> usize {
+        match &self.kind {
+            RecoveryEditKind::Wrap { prefix, suffix } => prefix.len() + suffix.len(),
+            RecoveryEditKind::Replace { text } => text.len().saturating_sub(self.range.len()),
+            RecoveryEditKind::Blank => 0,
+        }
+    }
+}
+
+/// A way of neutralizing compile errors in the synth.
+pub trait RecoveryStrategy {
+    /// Name reported with the ranges the strategy recovered.
+    fn name(&self) -> &'static str;
+
+    /// Proposes edits neutralizing `errors`, given as synth ranges, or none if
+    /// the strategy does not apply to them.
+    fn recover(
+        &self,
+        synth: &str,
+        errors: &[Range<usize>],
+        regions: &RecoveryRegions,
+    ) -> Vec<RecoveryEdit>;
+}
+
+/// Wraps errors inside equations in a red-text marker.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MathMarking;
+
+impl MathMarking {
+    const PREFIX: &'static str = "#math.italic(text(fill:theme.error)[";
+    const SUFFIX: &'static str = "])";
+}
+
+impl RecoveryStrategy for MathMarking {
+    fn name(&self) -> &'static str {
+        "math-marking"
+    }
+
+    fn recover(
+        &self,
+        _synth: &str,
+        errors: &[Range<usize>],
+        regions: &RecoveryRegions,
+    ) -> Vec<RecoveryEdit> {
+        errors
+            .iter()
+            .enumerate()
+            .filter(|(_, error)| {
+                regions
+                    .equations
+                    .iter()
+                    .any(|equation| touches(equation, error))
+            })
+            .map(|(idx, error)| RecoveryEdit {
+                kind: RecoveryEditKind::Wrap {
+                    prefix: Self::PREFIX,
+                    suffix: Self::SUFFIX,
+                },
+                range: error.clone(),
+                error: idx,
+            })
+            .collect()
+    }
+}
+
+/// Replaces the innermost failing expression outside equations with a
+/// red-text marker showing its source.
 ///
-/// Returns the indices of the removed blocks, or an empty vector if no error
-/// blocks were found.
-#[typst_macros::time]
-pub fn remove_errornous_block(
-    blocks: &[AstBlock],
-    source_diagnostics: &EcoVec<SourceDiagnostic>,
-    context: &mut SourceContext,
-    world: &mut MnemoWorld,
-) -> Vec<usize> {
-    let error_ranges = source_diagnostics
-        .iter()
-        .filter_map(|diagnostic| {
-            map_synth_span(
-                diagnostic.span,
-                diagnostic.severity == Severity::Error,
-                &diagnostic.trace,
-                context,
-                world,
+/// The expression is the node at the error, widened to the nearest function
+/// call, field access or identifier, and then to the whole call or access
+/// chain it heads. Embedded expressions keep their `#`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExpressionMarking;
+
+impl ExpressionMarking {
+    const PREFIX: &'static str = "text(fill:theme.error,raw(\"";
+    const SUFFIX: &'static str = "\"))";
+
+    fn marker(source: &str, embedded: bool) -> String {
+        let mut marker = String::from(if embedded { "#" } else { "" });
+        marker.push_str(Self::PREFIX);
+
+        for ch in source.chars() {
+            match ch {
+                '\\' => marker.push_str("\\\\"),
+                '"' => marker.push_str("\\\""),
+                '\n' => marker.push_str("\\n"),
+                '\r' => marker.push_str("\\r"),
+                _ => marker.push(ch),
+            }
+        }
+
+        marker.push_str(Self::SUFFIX);
+        marker
+    }
+}
+
+impl RecoveryStrategy for ExpressionMarking {
+    fn name(&self) -> &'static str {
+        "expression-marking"
+    }
+
+    fn recover(
+        &self,
+        synth: &str,
+        errors: &[Range<usize>],
+        regions: &RecoveryRegions,
+    ) -> Vec<RecoveryEdit> {
+        let root = typst_syntax::parse(synth);
+        let root = LinkedNode::new(&root);
+
+        errors
+            .iter()
+            .enumerate()
+            .filter(|(_, error)| {
+                !regions
+                    .equations
+                    .iter()
+                    .any(|equation| touches(equation, error))
+            })
+            .filter_map(|(idx, error)| {
+                let (range, embedded) = failing_expression(&root, error)?;
+
+                regions
+                    .blocks
+                    .iter()
+                    .any(|block| block.start <= range.start && range.end <= block.end)
+                    .then(|| RecoveryEdit {
+                        kind: RecoveryEditKind::Replace {
+                            text: Self::marker(&synth[range.clone()], embedded),
+                        },
+                        range,
+                        error: idx,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The synth range of the expression to mark for an error, and whether it is
+/// embedded in markup with a `#`, which the range then includes.
+fn failing_expression(root: &LinkedNode, error: &Range<usize>) -> Option<(Range<usize>, bool)> {
+    let mut node = root.clone();
+    while let Some(child) = node.children().find(|child| {
+        let range = child.range();
+        range.start <= error.start && error.end <= range.end
+    }) {
+        node = child;
+    }
+
+    let mut expr = iter::successors(Some(node), |node| node.parent().cloned())
+        .take_while(|node| {
+            !matches!(
+                node.kind(),
+                SyntaxKind::Markup | SyntaxKind::Code | SyntaxKind::Math
             )
         })
-        .collect::<FxHashSet<_>>();
+        .find(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::FuncCall | SyntaxKind::FieldAccess | SyntaxKind::Ident
+            )
+        })?;
 
-    let (indicies, synth_ranges): (Vec<usize>, Vec<Range<usize>>) = blocks
-        .iter()
-        .enumerate()
-        .filter_map(|(idx, block)| {
-            let raw_range = &block.range;
+    while let Some(parent) = expr
+        .parent()
+        .filter(|parent| matches!(parent.kind(), SyntaxKind::FuncCall | SyntaxKind::FieldAccess))
+    {
+        expr = parent.clone();
+    }
 
-            let synth_range_start = context.map_raw_to_synth_from_left(raw_range.start);
-            let synth_range_end = context.map_raw_to_synth_from_right(raw_range.end);
-            let synth_range = synth_range_start..synth_range_end;
+    let range = expr.range();
+
+    match expr.prev_sibling() {
+        Some(hash)
+            if hash.kind() == SyntaxKind::Hash
+                && hash.range().end == range.start
+                && expr.parent_kind() == Some(SyntaxKind::Markup) =>
+        {
+            Some((hash.range().start..range.end, true))
+        }
+        _ => Some((range, false)),
+    }
+}
+
+/// Blanks the blocks that contain errors.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlockBlanking;
+
+impl RecoveryStrategy for BlockBlanking {
+    fn name(&self) -> &'static str {
+        "block-blanking"
+    }
 
-            let in_block = error_ranges.iter().any(|error_range| {
-                (synth_range_start <= error_range.start && synth_range_end >= error_range.start)
-                    || (synth_range_start <= error_range.end && synth_range_end >= error_range.end)
-            });
+    fn recover(
+        &self,
+        _synth: &str,
+        errors: &[Range<usize>],
+        regions: &RecoveryRegions,
+    ) -> Vec<RecoveryEdit> {
+        regions
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                let error = errors.iter().position(|error| touches(block, error))?;
 
-            in_block.then_some((idx, synth_range))
+                Some(RecoveryEdit {
+                    kind: RecoveryEditKind::Blank,
+                    range: block.clone(),
+                    error,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether either end of `range` falls inside `region`, ends included.
+fn touches(region: &Range<usize>, range: &Range<usize>) -> bool {
+    region.contains(&range.start)
+        || region.end == range.start
+        || region.contains(&range.end)
+        || region.end == range.end
+}
+
+/// The recovery engine: picks the edits of each attempt and decides when to
+/// give up.
+pub struct Recovery {
+    strategies: Vec<Box<dyn RecoveryStrategy>>,
+    attempts: usize,
+    /// Edits applied so far, with their ranges in the current synth.
+    applied: Vec<RecoveryEdit>,
+}
+
+impl Default for Recovery {
+    /// Math marking, then expression marking, then block blanking.
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(MathMarking),
+            Box::new(ExpressionMarking),
+            Box::new(BlockBlanking),
+        ])
+    }
+}
+
+impl Recovery {
+    /// An engine trying `strategies` in order.
+    #[must_use]
+    pub fn new(strategies: Vec<Box<dyn RecoveryStrategy>>) -> Self {
+        Self {
+            strategies,
+            attempts: 0,
+            applied: Vec::new(),
+        }
+    }
+
+    /// Picks the edits for the next attempt, from the first strategy with new
+    /// edits to propose. The edits do not overlap.
+    ///
+    /// Returns `None` when recovery should give up. Otherwise, the caller is
+    /// expected to apply the edits before compiling again.
+    pub fn next_edits(
+        &mut self,
+        synth: &str,
+        errors: &[Range<usize>],
+        regions: &RecoveryRegions,
+    ) -> Option<(&'static str, Vec<RecoveryEdit>)> {
+        if self.attempts >= MAX_ATTEMPTS {
+            crate::error!("COULD NOT CONVERGE AFTER {MAX_ATTEMPTS} ATTEMPTS ‼️");
+
+            return None;
+        }
+
+        self.attempts += 1;
+
+        let candidate = self.strategies.iter().find_map(|strategy| {
+            let mut edits = Vec::<RecoveryEdit>::new();
+
+            for edit in strategy.recover(synth, errors, regions) {
+                let overlaps = edits.iter().any(|other| {
+                    edit.range.start < other.range.end && other.range.start < edit.range.end
+                });
+
+                if !overlaps && !self.was_tried(&edit) {
+                    edits.push(edit);
+                }
+            }
+
+            (!edits.is_empty()).then(|| (strategy.name(), edits))
+        });
+
+        let Some((name, edits)) = candidate else {
+            crate::error!("NO RECOVERABLE ERRORS FOUND ‼️");
+
+            return None;
+        };
+
+        self.record(&edits);
+
+        Some((name, edits))
+    }
+
+    fn was_tried(&self, edit: &RecoveryEdit) -> bool {
+        self.applied.iter().any(|applied| match (&edit.kind, &applied.kind) {
+            (RecoveryEditKind::Blank, RecoveryEditKind::Blank) => applied.range == edit.range,
+            (RecoveryEditKind::Blank, _) | (_, RecoveryEditKind::Blank) => false,
+            _ => {
+                let applied = applied.applied_range();
+
+                applied.start <= edit.range.start && edit.range.end <= applied.end
+            }
         })
-        .unzip();
+    }
 
-    for synth_range in synth_ranges {
-        let start_byte = synth_range.start;
-        let end_byte = synth_range.end;
+    /// Records edits about to be applied, shifting the ranges of earlier ones
+    /// past the text they insert.
+    fn record(&mut self, edits: &[RecoveryEdit]) {
+        let mut descending = edits.iter().collect::<Vec<_>>();
+        descending.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
 
-        // fill block with whitespace to stablize ranges
-        let source = context.synth_source_mut(world).unwrap();
-        let byte_length = end_byte - start_byte;
-        let whitespace = " ".repeat(byte_length.saturating_sub(1)) + "\n";
-        source.edit(start_byte..end_byte, &whitespace);
+        for edit in descending {
+            let delta = edit.delta();
+
+            for applied in &mut self.applied {
+                if applied.range.start >= edit.range.end {
+                    applied.range = (applied.range.start + delta)..(applied.range.end + delta);
+                } else if applied.range.end > edit.range.start {
+                    applied.range.end += delta;
+                }
+            }
+        }
+
+        self.applied.extend(edits.iter().map(|edit| RecoveryEdit {
+            range: shift_by_edits(&edit.range, edits),
+            ..edit.clone()
+        }));
     }
+}
 
-    indicies
+/// Where a range of the synth before `edits` ends up after them, for a range
+/// that is one of the edits' own.
+fn shift_by_edits(range: &Range<usize>, edits: &[RecoveryEdit]) -> Range<usize> {
+    let delta = edits
+        .iter()
+        .filter(|edit| edit.range.end <= range.start)
+        .map(RecoveryEdit::delta)
+        .sum::<usize>();
+
+    (range.start + delta)..(range.end + delta)
 }
 
-/// Tries to mark the specific expressions containing errors and wraps it in a
-/// red text expression for visual feedback in the rendered output.
-///
-/// If marking is unsuccessful (e.g., due to complex expressions or multiple
-/// errors), it falls back to removing the entire block containing the error, as
-/// implemented in `remove_errornous_block`.
-#[typst_macros::time]
-pub fn try_mark_errornous(
-    source_diagnostics: &EcoVec<SourceDiagnostic>,
-    eq_ranges: &[Range<usize>],
+/// Text that replaces `text` in a blanked range: spaces, keeping line breaks.
+fn blank(text: &str) -> String {
+    text.bytes()
+        .map(|byte| if byte == b'\n' { '\n' } else { ' ' })
+        .collect()
+}
+
+/// Applies non-overlapping edits to a synth string.
+pub fn apply_to_string(synth: &mut String, edits: &[RecoveryEdit]) {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+
+    for edit in edits {
+        match &edit.kind {
+            RecoveryEditKind::Wrap { prefix, suffix } => {
+                synth.insert_str(edit.range.end, suffix);
+                synth.insert_str(edit.range.start, prefix);
+            }
+            RecoveryEditKind::Replace { text } => synth.replace_range(edit.range.clone(), text),
+            RecoveryEditKind::Blank => {
+                let blanked = blank(&synth[edit.range.clone()]);
+                synth.replace_range(edit.range.clone(), &blanked);
+            }
+        }
+    }
+}
+
+/// Applies non-overlapping edits to a note's synth source, keeping its index
+/// mapper in sync.
+pub fn apply_to_context(
+    edits: &[RecoveryEdit],
     context: &mut SourceContext,
     world: &mut MnemoWorld,
-) -> MarkedErrors {
-    let pre_text = "#math.italic(text(fill:theme.error)[";
-    let post_text = "])";
-    let pre_text_len = pre_text.len();
-    let post_text_len = post_text.len();
-    let total_wrap_len = pre_text_len + post_text_len;
-
-    if eq_ranges.is_empty() {
-        return MarkedErrors {
-            marks: Vec::new(),
-            pre_text_len,
-            post_text_len,
-            total_wrap_len,
-        };
+) {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+
+    for edit in edits {
+        let source = context.synth_source_mut(world).unwrap();
+
+        match &edit.kind {
+            RecoveryEditKind::Wrap { prefix, suffix } => {
+                source.edit(edit.range.end..edit.range.end, suffix);
+                source.edit(edit.range.start..edit.range.start, prefix);
+            }
+            RecoveryEditKind::Replace { text } => {
+                source.edit(edit.range.clone(), text);
+            }
+            RecoveryEditKind::Blank => {
+                let blanked = blank(&source.text()[edit.range.clone()]);
+                source.edit(edit.range.clone(), &blanked);
+            }
+        }
+
+        map_error_mark_index(edit, context);
     }
+}
 
-    let eq_ranges = eq_ranges
-        .iter()
-        .map(|eq_range| {
-            let synth_start = context.map_raw_to_synth_from_left(eq_range.start);
-            let synth_end = context.map_raw_to_synth_from_right(eq_range.end);
+/// Records a marker applied to a note's synth in its index mapper, so the
+/// marker maps to the raw text it stands for. Blanking keeps every index, and
+/// needs no remapping.
+pub fn map_error_mark_index(edit: &RecoveryEdit, context: &mut SourceContext) {
+    match &edit.kind {
+        RecoveryEditKind::Wrap { prefix, suffix } => {
+            context
+                .index_mapper
+                .wrap_synth(edit.range.clone(), prefix.len(), suffix.len());
+        }
+        RecoveryEditKind::Replace { text } => {
+            context
+                .index_mapper
+                .replace_synth(edit.range.clone(), text.len());
+        }
+        RecoveryEditKind::Blank => {}
+    }
+}
 
-            synth_start..synth_end
-        })
-        .collect::<Vec<_>>();
+/// Maps the spans of compile diagnostics to synth ranges.
+#[must_use]
+pub fn synth_error_ranges(
+    diagnostics: &[SourceDiagnostic],
+    context: &SourceContext,
+    world: &MnemoWorld,
+) -> Vec<Range<usize>> {
+    synth_errors(diagnostics, context, world)
+        .into_iter()
+        .map(|(range, _)| range)
+        .collect()
+}
 
-    let error_ranges = source_diagnostics
+/// Like [`synth_error_ranges`], keeping the diagnostic each range is from.
+fn synth_errors<'a>(
+    diagnostics: &'a [SourceDiagnostic],
+    context: &SourceContext,
+    world: &MnemoWorld,
+) -> Vec<(Range<usize>, &'a SourceDiagnostic)> {
+    diagnostics
         .iter()
         .filter_map(|diagnostic| {
-            map_synth_span(
+            let range = map_synth_span(
                 diagnostic.span,
                 diagnostic.severity == Severity::Error,
                 &diagnostic.trace,
                 context,
                 world,
-            )
+            )?;
+
+            Some((range, diagnostic))
         })
-        .filter(|range| {
-            #[allow(clippy::suspicious_operation_groupings)]
-            eq_ranges.iter().any(|eq_range| {
-                (range.start >= eq_range.start && range.start <= eq_range.end)
-                    || (range.end >= eq_range.start && range.end <= eq_range.end)
+        .collect()
+}
+
+/// Runs one recovery attempt after a failed compile of a note's synth: maps
+/// the errors into the synth, asks the engine for edits, and applies them.
+///
+/// Blocks that get blanked are removed from `blocks`. Returns what each edit
+/// recovered, or `None` when recovery gave up.
+#[typst_macros::time]
+pub fn recover(
+    recovery: &mut Recovery,
+    diagnostics: &[SourceDiagnostic],
+    blocks: &mut Vec<AstBlock>,
+    equations: &[Range<usize>],
+    context: &mut SourceContext,
+    world: &mut MnemoWorld,
+) -> Option<Vec<TypstRecovered>> {
+    let errors = synth_errors(diagnostics, context, world);
+    let ranges = errors.iter().map(|(range, _)| range.clone()).collect::<Vec<_>>();
+    let regions = RecoveryRegions::new(blocks, equations, context);
+    let synth = context.synth_source(world)?.text();
+
+    let (strategy, edits) = recovery.next_edits(synth, &ranges, &regions)?;
+
+    // Ranges are mapped back to the raw source before the edits move anchors.
+    let raw_lines = context.raw_source(world)?.lines();
+    let recovered = edits
+        .iter()
+        .filter_map(|edit| {
+            let raw_start = context.map_synth_to_raw_from_left(edit.range.start);
+            let raw_end = context.map_synth_to_raw_from_right(edit.range.end);
+            let (_, diagnostic) = errors.get(edit.error)?;
+
+            Some(TypstRecovered {
+                range: raw_lines.byte_to_utf16(raw_start)?..raw_lines.byte_to_utf16(raw_end)?,
+                strategy: strategy.to_string(),
+                diagnostic: TypstDiagnostic::from_diagnostics(
+                    eco_vec![(*diagnostic).clone()],
+                    context,
+                    world,
+                )
+                .into_iter()
+                .next()?,
             })
         })
-        .collect::<Vec<_>>();
+        .collect();
 
-    let marks = error_ranges
-        .into_iter()
-        .map(|synth_range| {
-            let source = context.synth_source_mut(world).unwrap();
-            let original_text = source.text()[synth_range.clone()].to_string();
-            // crate::log!("[MARKING]:\n{}", original_text);
+    let mut blanked = regions.blocks.iter().map(|block| {
+        edits
+            .iter()
+            .any(|edit| edit.kind == RecoveryEditKind::Blank && edit.range == *block)
+    });
+    blocks.retain(|_| !blanked.next().unwrap_or_default());
 
-            // Wrap the original text in a red text expression
-            let marked_text = format!("{pre_text}{original_text}{post_text}");
-            source.edit(synth_range.clone(), &marked_text);
+    apply_to_context(&edits, context, world);
 
-            context
-                .index_mapper
-                .bump_synth_from(synth_range.end, total_wrap_len);
+    Some(recovered)
+}
 
-            // crate::log!("[NEW SOURCE]:\n{}", &source.text());
+/// Ranges from `(start, end)` pairs.
+#[cfg(test)]
+fn ranges(bounds: &[(usize, usize)]) -> Vec<Range<usize>> {
+    bounds.iter().map(|&(start, end)| start..end).collect()
+}
 
-            ErrorMark {
-                text: original_text,
-                synth_range: synth_range.start..(synth_range.end + total_wrap_len),
-            }
-        })
-        .collect();
+#[test]
+fn test_block_blanking_keeps_length() {
+    let mut synth = String::from("a\n#f(\n)\nb\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 2), (2, 8), (8, 10)]),
+        equations: Vec::new(),
+    };
 
-    MarkedErrors {
-        marks,
-        pre_text_len,
-        post_text_len,
-        total_wrap_len,
-    }
+    let mut recovery = Recovery::new(vec![Box::new(BlockBlanking)]);
+    let (strategy, edits) = recovery.next_edits(&synth, &ranges(&[(3, 4)]), &regions).unwrap();
+    apply_to_string(&mut synth, &edits);
+
+    assert_eq!(strategy, "block-blanking");
+    assert_eq!(synth, "a\n   \n \nb\n");
 }
 
-pub fn map_error_mark_index(marked_errors: &MarkedErrors, context: &mut SourceContext) {
-    for mark in &marked_errors.marks {
-        // crate::log!("before: {:?}", &context.index_mapper);
-        // crate::log!("delta range: {synth_range:?}");
+#[test]
+fn test_math_marking_before_blanking() {
+    let mut synth = String::from("$a + #x$\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 9)]),
+        equations: ranges(&[(0, 8)]),
+    };
 
-        let raw_start = context
-            .index_mapper
-            .map_synth_to_raw_from_right(mark.synth_range.start);
-        context.index_mapper.push_raw_to_synth(
-            raw_start,
-            mark.synth_range.start + marked_errors.pre_text_len,
-        );
-        context
-            .index_mapper
-            .push_raw_to_synth(raw_start, mark.synth_range.start);
+    let mut recovery = Recovery::default();
+    let (strategy, edits) = recovery.next_edits(&synth, &ranges(&[(5, 7)]), &regions).unwrap();
+    apply_to_string(&mut synth, &edits);
 
-        let raw_end = context
-            .index_mapper
-            .map_synth_to_raw_from_left(mark.synth_range.end);
-        context
-            .index_mapper
-            .push_raw_to_synth(raw_end, mark.synth_range.end + marked_errors.total_wrap_len);
-        context
-            .index_mapper
-            .push_raw_to_synth(raw_end, mark.synth_range.end + marked_errors.pre_text_len);
+    assert_eq!(strategy, "math-marking");
+    assert_eq!(synth, "$a + #math.italic(text(fill:theme.error)[#x])$\n");
+}
 
-        // crate::log!("after: {:?}", &context.index_mapper);
-    }
+#[test]
+fn test_expression_marking_in_markup() {
+    let mut synth = String::from("a #foo() b\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 11)]),
+        equations: Vec::new(),
+    };
+
+    let mut recovery = Recovery::default();
+    let (strategy, edits) = recovery.next_edits(&synth, &ranges(&[(3, 6)]), &regions).unwrap();
+    apply_to_string(&mut synth, &edits);
+
+    assert_eq!(strategy, "expression-marking");
+    assert_eq!(synth, "a #text(fill:theme.error,raw(\"#foo()\")) b\n");
 }
 
-#[derive(Debug)]
-pub struct MarkedErrors {
-    pub marks: Vec<ErrorMark>,
-    pub pre_text_len: usize,
-    pub post_text_len: usize,
-    pub total_wrap_len: usize,
+#[test]
+fn test_expression_marking_in_code() {
+    let mut synth = String::from("#let x = foo.bar\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 17)]),
+        equations: Vec::new(),
+    };
+
+    let mut recovery = Recovery::default();
+    let (_, edits) = recovery.next_edits(&synth, &ranges(&[(9, 12)]), &regions).unwrap();
+    apply_to_string(&mut synth, &edits);
+
+    assert_eq!(synth, "#let x = text(fill:theme.error,raw(\"foo.bar\"))\n");
+}
+
+#[test]
+fn test_falls_back_when_marking_does_not_help() {
+    let synth = String::from("$a + #x$\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 9)]),
+        equations: ranges(&[(0, 8)]),
+    };
+
+    let mut recovery = Recovery::default();
+    let (_, edits) = recovery.next_edits(&synth, &ranges(&[(5, 7)]), &regions).unwrap();
+
+    let mut marked = synth;
+    apply_to_string(&mut marked, &edits);
+
+    // The error is still reported inside the marker.
+    let marked_regions = RecoveryRegions {
+        blocks: ranges(&[(0, 47)]),
+        equations: ranges(&[(0, 46)]),
+    };
+    let (strategy, _) = recovery
+        .next_edits(&marked, &ranges(&[(41, 43)]), &marked_regions)
+        .unwrap();
+
+    assert_eq!(strategy, "block-blanking");
+}
+
+#[test]
+fn test_gives_up_without_progress() {
+    let synth = String::from("#f(\n");
+    let regions = RecoveryRegions {
+        blocks: ranges(&[(0, 4)]),
+        equations: Vec::new(),
+    };
+
+    let mut recovery = Recovery::new(vec![Box::new(BlockBlanking)]);
+    assert!(recovery.next_edits(&synth, &ranges(&[(1, 3)]), &regions).is_some());
+    assert!(recovery.next_edits(&synth, &ranges(&[(1, 3)]), &regions).is_none());
 }
 
-#[derive(Debug)]
-pub struct ErrorMark {
-    pub text: String,
-    pub synth_range: Range<usize>,
+#[test]
+fn test_apply_to_context_keeps_mapping() {
+    use typst_syntax::{FileId, RootedPath, VirtualPath, VirtualRoot};
+
+    use crate::source::sync_source_context;
+
+    let raw = "a #foo() b $x + #y$ c\n";
+
+    let mut world = MnemoWorld::default();
+    let id = FileId::new(RootedPath::new(
+        VirtualRoot::Project,
+        VirtualPath::new("note.typ").unwrap(),
+    ));
+    let mut context = SourceContext::new(id, String::from("space"));
+    world.insert_source(context.raw_id, String::new());
+    world.insert_source(context.synth_id, String::new());
+
+    let synth = sync_source_context(raw, String::new(), &mut context, &mut world).synth;
+    world.insert_source(context.synth_id, synth.clone());
+
+    let call = synth.find("#foo()").unwrap();
+    let math = synth.find("#y").unwrap();
+    let marker = "#text(fill:theme.error)[`#foo()`]";
+    let edits = [
+        RecoveryEdit {
+            kind: RecoveryEditKind::Replace {
+                text: marker.to_string(),
+            },
+            range: call..call + 6,
+            error: 0,
+        },
+        RecoveryEdit {
+            kind: RecoveryEditKind::Wrap {
+                prefix: "#math.italic(text(fill:theme.error)[",
+                suffix: "])",
+            },
+            range: math..math + 2,
+            error: 1,
+        },
+    ];
+
+    let mut expected = synth;
+    apply_to_string(&mut expected, &edits);
+    apply_to_context(&edits, &mut context, &mut world);
+
+    let synth = context.synth_source(&world).unwrap().text();
+    assert_eq!(synth, expected);
+
+    // Text outside the markers maps back to where it was typed.
+    for needle in ["a ", " b", "$x", "$ c"] {
+        let synth_idx = synth.find(needle).unwrap() + 1;
+        let raw_idx = raw.find(needle).unwrap() + 1;
+
+        assert_eq!(context.map_synth_to_raw_from_left(synth_idx), raw_idx);
+        assert_eq!(context.map_raw_to_synth_from_left(raw_idx), synth_idx);
+    }
+
+    // Each marker spans the raw text it stands for.
+    let replaced = synth.find(marker).unwrap();
+    let call = raw.find("#foo()").unwrap();
+    assert_eq!(context.map_synth_to_raw_from_right(replaced), call);
+    assert_eq!(context.map_synth_to_raw_from_left(replaced + marker.len()), call + 6);
+
+    let wrapped = synth.find("#math.italic").unwrap();
+    let y = raw.find("#y").unwrap();
+    assert_eq!(context.map_synth_to_raw_from_right(wrapped), y);
+    assert_eq!(context.map_synth_to_raw_from_right(synth.find("#y]").unwrap()), y);
+    assert_eq!(context.map_synth_to_raw_from_left(synth.find("])$").unwrap() + 2), y + 2);
 }
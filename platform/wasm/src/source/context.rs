@@ -1,10 +1,17 @@
+use time::Date;
 use typst_html::HtmlDocument;
 use typst_layout::PagedDocument;
 use typst_syntax::{FileId, RootedPath, Source};
 
-use crate::{source::IndexMapper, theme::ThemeColors, world::MnemoWorld};
+use crate::{
+    renderer::paged::{FrameBounds, pdf::PdfExportOptions},
+    source::{IndexMapper, SynthCache},
+    style::SpaceStyle,
+    theme::ThemeColors,
+    world::MnemoWorld,
+};
 
-/// Per-space configuration for rendering (fonts, theme, locale).
+/// Per-space configuration for rendering (fonts, theme, locale, style).
 #[derive(Debug, Hash)]
 pub struct SpaceContext {
     /// Default font for this space.
@@ -17,6 +24,11 @@ pub struct SpaceContext {
     pub theme: ThemeColors,
     /// Locale for this space.
     pub locale: String,
+    /// Typography for this space.
+    pub style: SpaceStyle,
+    /// Project path of the space library, a source whose definitions every
+    /// note of the space imports after the prelude.
+    pub library: Option<String>,
 }
 
 impl SpaceContext {
@@ -28,6 +40,8 @@ impl SpaceContext {
             code_font: Some(String::from("Maple Mono")),
             theme: ThemeColors::default(),
             locale: String::from("en"),
+            style: SpaceStyle::default(),
+            library: None,
         }
     }
 }
@@ -71,6 +85,11 @@ pub struct SourceContext {
     /// and the synth. Rebuilt on each call to `sync_source_context`.
     pub index_mapper: IndexMapper,
 
+    /// The last synth built for this note. Reused when the raw text and
+    /// prelude are unchanged, and patched in place by
+    /// [`TypstState::apply_edits`].
+    pub synth_cache: Option<SynthCache>,
+
     /// The most recently compiled paged document for this note, if any.
     /// Cached here so hover and jump-to-source queries can avoid recompiling.
     pub paged_document: Option<PagedDocument>,
@@ -78,6 +97,10 @@ pub struct SourceContext {
     /// The most recently compiled HTML document for this note, if any.
     pub html_document: Option<HtmlDocument>,
 
+    /// Placement of each frame returned by the last paged compile, in the
+    /// same order.
+    pub frame_bounds: Vec<FrameBounds>,
+
     /// Rendered page width, as a Typst dimension string (e.g. `"420pt"`
     /// or `"auto"`). Updated by [`TypstState::resize`].
     pub width: String,
@@ -89,6 +112,20 @@ pub struct SourceContext {
     /// Body text size in points. Drives `#set text(size: ...)` in the
     /// generated prelude.
     pub text_size: f64,
+
+    /// Page setup of the page mode preview. Set by
+    /// [`TypstState::set_page_mode`], and swapped for the export's own setup
+    /// while [`TypstState::render_pdf`] compiles.
+    pub pdf_options: PdfExportOptions,
+
+    /// Whether paged compiles lay the note out on real pages using
+    /// `pdf_options`, as a print preview, instead of one page that grows with
+    /// its content.
+    pub page_mode: bool,
+
+    /// The calendar date the note belongs to, like a daily note's day.
+    /// `datetime.today()` returns it instead of the current date when set.
+    pub date: Option<Date>,
 }
 
 impl SourceContext {
@@ -105,11 +142,16 @@ impl SourceContext {
             space_id,
             unstable_synth: String::new(),
             index_mapper: IndexMapper::default(),
+            synth_cache: None,
             paged_document: None,
             html_document: None,
+            frame_bounds: Vec::new(),
             width: String::from("auto"),
             height: None,
             text_size: 16.0,
+            pdf_options: PdfExportOptions::default(),
+            page_mode: false,
+            date: None,
         }
     }
 
@@ -129,6 +171,27 @@ impl SourceContext {
         world.files.get_mut(&self.raw_id)?.source_mut()
     }
 
+    /// Points the world at this note, so Typst sees its sources, its space's
+    /// fonts and time zone, its date and its inputs.
+    ///
+    /// Anything that hands the world to Typst for a note, compiling or an IDE
+    /// query, must enter the note first.
+    pub fn enter(&self, world: &mut MnemoWorld) {
+        world.synth_id = Some(self.synth_id);
+        world.raw_id = Some(self.raw_id);
+        world.space_id = Some(self.space_id.clone());
+        world.date = self.date;
+    }
+
+    /// Whether a synth byte offset falls in the prelude the synth was last
+    /// built with.
+    #[must_use]
+    pub fn in_prelude(&self, synth_idx: usize) -> bool {
+        self.synth_cache
+            .as_ref()
+            .is_some_and(|cache| synth_idx < cache.prelude.len())
+    }
+
     #[must_use]
     pub fn map_synth_to_raw_from_right(&self, synth_idx: usize) -> usize {
         self.index_mapper.map_synth_to_raw_from_right(synth_idx)
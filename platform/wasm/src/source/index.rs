@@ -1,4 +1,4 @@
-use std::cmp;
+use std::{cmp, ops::Range};
 
 /// Maps byte offsets between the user's raw text and the synthesized file
 /// Typst compiles.
@@ -34,7 +34,7 @@ use std::cmp;
 /// - `*_from_left` walks anchors forward until it finds the pair bracketing the
 ///   query. Use this for click-to-jump, where you want the position as seen
 ///   from inside the content.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct IndexMapper {
     anchors: Vec<(usize, usize)>,
 }
@@ -188,6 +188,83 @@ impl IndexMapper {
             }
         }
     }
+
+    /// Record that `prefix_len` bytes were inserted before `synth_range` and
+    /// `suffix_len` bytes after it, leaving the text in between unchanged.
+    ///
+    /// Positions in the inserted text map to the edges of the wrapped text.
+    pub fn wrap_synth(&mut self, synth_range: Range<usize>, prefix_len: usize, suffix_len: usize) {
+        let raw_start = self.map_synth_to_raw_from_right(synth_range.start);
+        let raw_end = self.map_synth_to_raw_from_left(synth_range.end);
+
+        self.bump_synth_from(synth_range.end, suffix_len);
+        self.bump_synth_from(synth_range.start, prefix_len);
+
+        let wrapped_end = synth_range.end + prefix_len;
+
+        for anchor in [
+            (raw_start, synth_range.start),
+            (raw_start, synth_range.start + prefix_len),
+            (raw_end, wrapped_end),
+            (raw_end, wrapped_end + suffix_len),
+        ] {
+            let idx = self.anchors.partition_point(|&other| other < anchor);
+            self.anchors.insert(idx, anchor);
+        }
+    }
+
+    /// Record that the text in `synth_range` was replaced by `len` bytes, at
+    /// least as many as it had.
+    ///
+    /// The replacement maps to the whole raw range of the replaced text.
+    pub fn replace_synth(&mut self, synth_range: Range<usize>, len: usize) {
+        let raw_start = self.map_synth_to_raw_from_right(synth_range.start);
+        let raw_end = self.map_synth_to_raw_from_left(synth_range.end);
+
+        self.anchors.retain(|&(_, synth_byte)| {
+            synth_byte <= synth_range.start || synth_byte >= synth_range.end
+        });
+        self.bump_synth_from(synth_range.end, len.saturating_sub(synth_range.len()));
+
+        for anchor in [(raw_start, synth_range.start), (raw_end, synth_range.start + len)] {
+            let idx = self.anchors.partition_point(|&other| other < anchor);
+            self.anchors.insert(idx, anchor);
+        }
+    }
+
+    /// Replace the anchors of an edited region.
+    ///
+    /// Anchors with a raw index at or before `prefix_end` are kept, anchors at
+    /// or after `suffix_start` are shifted by `raw_delta` and `synth_delta`,
+    /// and everything in between is replaced by the anchors of `region`. Both
+    /// bounds are raw indices from before the edit; `None` means the region
+    /// reaches the start or end of the source.
+    pub fn splice(
+        &mut self,
+        prefix_end: Option<usize>,
+        suffix_start: Option<usize>,
+        region: IndexMapper,
+        raw_delta: isize,
+        synth_delta: isize,
+    ) {
+        let prefix_len = prefix_end.map_or(0, |prefix_end| {
+            self.anchors
+                .partition_point(|&(raw_byte, _)| raw_byte <= prefix_end)
+        });
+        let suffix_idx = suffix_start
+            .map_or(self.anchors.len(), |suffix_start| {
+                self.anchors
+                    .partition_point(|&(raw_byte, _)| raw_byte < suffix_start)
+            })
+            .max(prefix_len);
+
+        for (raw_byte, synth_byte) in &mut self.anchors[suffix_idx..] {
+            *raw_byte = raw_byte.saturating_add_signed(raw_delta);
+            *synth_byte = synth_byte.saturating_add_signed(synth_delta);
+        }
+
+        self.anchors.splice(prefix_len..suffix_idx, region.anchors);
+    }
 }
 
 #[test]
@@ -445,3 +522,57 @@ fn test_duplicate_synth_indices_different_raw() {
     assert_eq!(mapper.map_synth_to_raw_from_right(10), 5);
     assert_eq!(mapper.map_raw_to_synth_from_right(5), 10);
 }
+
+#[test]
+fn test_splice_region() {
+    let mut mapper = IndexMapper::default();
+    mapper.push_raw_to_synth_unchecked(0, 10);
+    mapper.push_raw_to_synth_unchecked(4, 14);
+    mapper.push_raw_to_synth_unchecked(6, 20);
+    mapper.push_raw_to_synth_unchecked(9, 23);
+    mapper.push_raw_to_synth_unchecked(12, 30);
+
+    // The block at raw 6..9 grew by two bytes in both sources
+    let mut region = IndexMapper::default();
+    region.push_raw_to_synth_unchecked(6, 20);
+    region.push_raw_to_synth_unchecked(11, 25);
+    mapper.splice(Some(4), Some(12), region, 2, 2);
+
+    // Prefix is untouched
+    assert_eq!(mapper.map_raw_to_synth_from_right(2), 12);
+    assert_eq!(mapper.map_raw_to_synth_from_right(4), 14);
+
+    // Region uses the new anchors
+    assert_eq!(mapper.map_raw_to_synth_from_right(6), 20);
+    assert_eq!(mapper.map_raw_to_synth_from_right(11), 25);
+
+    // Suffix is shifted
+    assert_eq!(mapper.map_raw_to_synth_from_right(14), 32);
+    assert_eq!(mapper.map_synth_to_raw_from_right(33), 15);
+}
+
+#[test]
+fn test_wrap_synth() {
+    let mut mapper = IndexMapper::default();
+    mapper.push_raw_to_synth_unchecked(0, 10);
+
+    // Raw `ab cd` at synth 10, with `cd` wrapped in `<<` and `>`.
+    mapper.wrap_synth(13..15, 2, 1);
+
+    assert_eq!(mapper.map_raw_to_synth_from_right(3), 15);
+    assert_eq!(mapper.map_synth_to_raw_from_right(15), 3);
+    assert_eq!(mapper.map_synth_to_raw_from_right(18), 5);
+}
+
+#[test]
+fn test_replace_synth() {
+    let mut mapper = IndexMapper::default();
+    mapper.push_raw_to_synth_unchecked(0, 10);
+
+    // Raw `ab cd ef` at synth 10, with `cd` replaced by six bytes.
+    mapper.replace_synth(13..15, 6);
+
+    assert_eq!(mapper.map_raw_to_synth_from_right(6), 20);
+    assert_eq!(mapper.map_synth_to_raw_from_right(19), 5);
+    assert_eq!(mapper.map_synth_to_raw_from_right(20), 6);
+}
@@ -18,10 +18,12 @@
 //!
 //! ## Invariant
 //!
-//! The synth is always rebuilt from scratch before each compile. It is never
-//! edited directly. All structural edits (error recovery whitespace, math error
-//! markers) are applied to the synth's `Source` object in place during the
-//! compile loop, then reverted or rebuilt before the next call.
+//! The synth is always restored from its last clean build before each
+//! compile. That build is cached on the [`SourceContext`] and either reused
+//! as-is, patched block by block by [`synth::apply_source_edits`], or
+//! rebuilt from scratch. All structural edits (error recovery whitespace, math
+//! error markers) are applied to the synth's `Source` object in place during
+//! the compile loop, then reverted or rebuilt before the next call.
 
 mod context;
 mod index;
@@ -29,4 +31,7 @@ mod synth;
 
 pub use context::{SourceContext, SpaceContext};
 pub use index::IndexMapper;
-pub use synth::{AstBlock, RenderTarget, SynthResult, sync_source_context, sync_source_state};
+pub use synth::{
+    AstBlock, RenderTarget, SynthCache, SynthResult, apply_source_edits, sync_source_context,
+    sync_source_state,
+};
@@ -1,9 +1,13 @@
-use std::ops::Range;
+use std::{cmp, hash::BuildHasher, ops::Range};
 
-use typst::{WorldExt, syntax::SyntaxKind};
+use rustc_hash::FxBuildHasher;
+use typst::{
+    WorldExt,
+    syntax::{LinkedNode, SyntaxKind, SyntaxNode},
+};
 
 use crate::{
-    bindings::TypstFileId,
+    bindings::{TypstFileId, TypstTextEdit},
     source::{IndexMapper, SourceContext},
     state::TypstState,
     world::MnemoWorld,
@@ -25,7 +29,7 @@ pub struct SynthResult {
 
 /// A top-level node or contiguous run of nodes from the raw source,
 /// corresponding to one independently renderable chunk.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AstBlock {
     /// Byte range in the **raw** source. This is the range the rendered chunk
     /// maps back to in the editor.
@@ -35,6 +39,97 @@ pub struct AstBlock {
     /// or nodes that share a line with their neighbours). Inline blocks
     /// are not wrapped in `#block(...)`.
     pub is_inline: bool,
+
+    /// Kind of the block's last node, which decides how the block is wrapped.
+    pub kind: Option<SyntaxKind>,
+
+    /// Hash of the block's raw text and `kind`. Two blocks with the same hash
+    /// emit the same synth text.
+    pub hash: u64,
+
+    /// Byte range of everything emitted for this block in the **synth**,
+    /// including its wrapper and trailing newline. Emitted blocks are
+    /// contiguous and start right after the prelude.
+    pub synth_range: Range<usize>,
+}
+
+/// The last synth built for a note, kept on its [`SourceContext`] so that
+/// unchanged text can skip the rebuild and edits can patch it in place.
+#[derive(Debug, Clone)]
+pub struct SynthCache {
+    /// The raw text the synth was built from.
+    pub raw: String,
+    /// The prelude the synth was built with.
+    pub prelude: String,
+    /// The full synth string.
+    pub synth: String,
+    /// The top-level blocks of `raw`, in source order.
+    pub blocks: Vec<AstBlock>,
+    /// Raw source ranges of all top-level `equation` nodes.
+    pub equation_ranges: Vec<Range<usize>>,
+    /// The index mapping between `raw` and `synth`.
+    pub index_mapper: IndexMapper,
+}
+
+impl SynthCache {
+    /// Replaces `self.blocks[replaced]` with `region`, emitting only the
+    /// region's blocks and shifting everything after it.
+    ///
+    /// `region` holds freshly collected blocks in the coordinates of the new
+    /// raw `text`, and `raw_delta` is the change in raw length.
+    pub fn splice_blocks(
+        &mut self,
+        replaced: Range<usize>,
+        mut region: Vec<AstBlock>,
+        text: &str,
+        raw_delta: isize,
+    ) {
+        let prefix = replaced.start.checked_sub(1).map(|idx| &self.blocks[idx]);
+        let suffix = self.blocks.get(replaced.end);
+
+        let synth_start = prefix.map_or(self.prelude.len(), |block| block.synth_range.end);
+        let synth_end = suffix.map_or(self.synth.len(), |block| block.synth_range.start);
+        let prefix_end = prefix.map(|block| block.range.end);
+        let suffix_start = suffix.map(|block| block.range.start);
+
+        let mut synth = String::new();
+        let mut index_mapper = IndexMapper::default();
+        emit_blocks(&mut region, text, &mut synth, &mut index_mapper);
+
+        index_mapper.bump_synth_from(0, synth_start);
+        for block in &mut region {
+            block.synth_range =
+                (block.synth_range.start + synth_start)..(block.synth_range.end + synth_start);
+        }
+
+        #[allow(clippy::cast_possible_wrap)]
+        let synth_delta = synth.len() as isize - (synth_end - synth_start) as isize;
+
+        self.synth.replace_range(synth_start..synth_end, &synth);
+        self.index_mapper.splice(
+            prefix_end,
+            suffix_start,
+            index_mapper,
+            raw_delta,
+            synth_delta,
+        );
+
+        let region_len = region.len();
+        self.blocks.splice(replaced.clone(), region);
+
+        for block in &mut self.blocks[replaced.start + region_len..] {
+            block.range = shift_range(&block.range, raw_delta);
+            block.synth_range = shift_range(&block.synth_range, synth_delta);
+        }
+    }
+
+    fn synth_result(&self) -> SynthResult {
+        SynthResult {
+            synth: self.synth.clone(),
+            blocks: self.blocks.clone(),
+            equation_ranges: self.equation_ranges.clone(),
+        }
+    }
 }
 
 /// Output target for rendering.
@@ -105,6 +200,13 @@ pub fn sync_source_state(
 /// the middle of a sentence) is collected into a single block. The
 /// `AstBlock::is_inline` flag is set on any block containing a node from the
 /// inline category.
+///
+/// ## Incremental rebuilds
+///
+/// Each block is hashed by its raw text and wrapping kind. When the prelude is
+/// unchanged, blocks matching the previous build at the start and (shifted) at
+/// the end of the note are kept as they are, and only the blocks between them
+/// are emitted again.
 #[typst_macros::time]
 pub fn sync_source_context(
     text: &str,
@@ -112,96 +214,287 @@ pub fn sync_source_context(
     context: &mut SourceContext,
     world: &mut MnemoWorld,
 ) -> SynthResult {
-    let mut ir = prelude;
+    context.enter(world);
 
-    context.index_mapper = IndexMapper::default();
-    // context.index_mapper.add_raw_to_synth(0, ir.len());
+    if let Some(cache) = &context.synth_cache
+        && cache.raw == text
+        && cache.prelude == prelude
+    {
+        context.index_mapper = cache.index_mapper.clone();
 
-    // context
-    //     .synth_source_mut(&mut world)
-    //     .unwrap()
-    //     .replace(&ir);
-    world.synth_id = Some(context.synth_id);
+        return cache.synth_result();
+    }
 
     context.raw_source_mut(world).unwrap().replace(text);
-    world.raw_id = Some(context.raw_id);
 
     let raw_source = context.raw_source(world).unwrap();
+    let text = raw_source.text();
+
+    let nodes = raw_source
+        .root()
+        .children()
+        .map(|node| (world.range(node.span()).unwrap(), node));
+    let (mut blocks, equation_ranges) = collect_blocks(nodes, text);
+
+    let cache = match context.synth_cache.take() {
+        // Only emit the blocks between the longest unchanged prefix and suffix
+        Some(mut cache) if cache.prelude == prelude => {
+            #[allow(clippy::cast_possible_wrap)]
+            let raw_delta = text.len() as isize - cache.raw.len() as isize;
+
+            let prefix_len = cache
+                .blocks
+                .iter()
+                .zip(&blocks)
+                .take_while(|(old, new)| old.hash == new.hash && old.range == new.range)
+                .count();
+            let suffix_len = cache.blocks[prefix_len..]
+                .iter()
+                .rev()
+                .zip(blocks[prefix_len..].iter().rev())
+                .take_while(|(old, new)| {
+                    old.hash == new.hash && shift_range(&old.range, raw_delta) == new.range
+                })
+                .count();
+
+            let region_end = blocks.len() - suffix_len;
+            let region = blocks.drain(prefix_len..region_end).collect();
+            let replaced_end = cache.blocks.len() - suffix_len;
+
+            cache.splice_blocks(prefix_len..replaced_end, region, text, raw_delta);
+            cache.equation_ranges = equation_ranges;
+            cache.raw = text.to_string();
+
+            cache
+        }
+        _ => {
+            let mut synth = prelude.clone();
+            let mut index_mapper = IndexMapper::default();
+            emit_blocks(&mut blocks, text, &mut synth, &mut index_mapper);
+
+            SynthCache {
+                raw: text.to_string(),
+                prelude,
+                synth,
+                blocks,
+                equation_ranges,
+                index_mapper,
+            }
+        }
+    };
+
+    // crate::log!("[RANGES]: {block_ranges:?}");
+
+    context.index_mapper = cache.index_mapper.clone();
+    let result = cache.synth_result();
+    context.synth_cache = Some(cache);
+
+    result
+}
+
+/// Applies text edits to the raw source and patches the cached synth.
+///
+/// Each edit's range is in UTF-16 code units of the text as left by the
+/// previous edit. The raw source is updated through `Source::edit`, so Typst
+/// only reparses the edited region.
+///
+/// Only the top-level blocks touched by the edits are collected and emitted
+/// again; the rest of the synth is left byte-for-byte unchanged and its
+/// `IndexMapper` anchors are shifted. A following compile with the edited text
+/// then reuses the patched synth instead of rebuilding it.
+#[typst_macros::time]
+pub fn apply_source_edits(
+    edits: &[TypstTextEdit],
+    context: &mut SourceContext,
+    world: &mut MnemoWorld,
+) {
+    let Some(raw_source) = context.raw_source_mut(world) else {
+        return;
+    };
+
+    if context
+        .synth_cache
+        .as_ref()
+        .is_some_and(|cache| cache.raw != raw_source.text())
+    {
+        context.synth_cache = None;
+    }
 
-    let children = raw_source.root().children();
+    let old_len = raw_source.text().len();
+    let mut changed: Option<Range<usize>> = None;
+
+    for edit in edits {
+        let raw_lines = raw_source.lines();
+        let (Some(start), Some(end)) = (
+            raw_lines.utf16_to_byte(edit.range.start),
+            raw_lines.utf16_to_byte(edit.range.end),
+        ) else {
+            continue;
+        };
+
+        let reparsed = raw_source.edit(start..end, &edit.text);
+        let inserted_end = start + edit.text.len();
+
+        // Keep a raw range (in the edited text) outside of which nothing
+        // changed, apart from being shifted.
+        changed = Some(match changed {
+            Some(range) => {
+                let range_end = if range.end >= end {
+                    range.end - end + inserted_end
+                } else if range.end > start {
+                    inserted_end
+                } else {
+                    range.end
+                };
+
+                cmp::min(range.start, start).min(reparsed.start)
+                    ..range_end.max(inserted_end).max(reparsed.end)
+            }
+            None => cmp::min(start, reparsed.start)..cmp::max(inserted_end, reparsed.end),
+        });
+    }
+
+    let Some(changed) = changed else {
+        return;
+    };
+
+    let Some(mut cache) = context.synth_cache.take() else {
+        return;
+    };
+
+    let raw_source = context.raw_source(world).unwrap();
     let text = raw_source.text();
 
+    #[allow(clippy::cast_possible_wrap)]
+    let raw_delta = text.len() as isize - old_len as isize;
+    let changed_end_old = changed.end.saturating_add_signed(-raw_delta);
+
+    // Blocks that end before the edit and start after it are kept. Blocks end
+    // at the newline that terminates them, so a deleted newline pulls the
+    // block before it into the edited region.
+    let prefix_len = cache
+        .blocks
+        .partition_point(|block| block.range.end < changed.start);
+    let suffix_start = cache
+        .blocks
+        .partition_point(|block| block.range.start <= changed_end_old)
+        .max(prefix_len);
+
+    // Walk from the newline node closing the last kept block to the start of
+    // the first shifted block.
+    let walk_start = prefix_len
+        .checked_sub(1)
+        .map_or(0, |idx| cache.blocks[idx].range.end);
+    let suffix_raw_start = cache.blocks.get(suffix_start).map(|block| block.range.start);
+    let walk_end = suffix_raw_start.map_or(text.len(), |start| {
+        start.saturating_add_signed(raw_delta)
+    });
+
+    let nodes = LinkedNode::new(raw_source.root())
+        .children()
+        .filter(|node| node.range().end > walk_start && node.offset() < walk_end)
+        .map(|node| (node.range(), node.get()));
+    let (region, region_eq_ranges) = collect_blocks(nodes, text);
+
+    cache.splice_blocks(prefix_len..suffix_start, region, text, raw_delta);
+
+    let mut equation_ranges = cache
+        .equation_ranges
+        .iter()
+        .filter(|range| range.start < walk_start)
+        .cloned()
+        .collect::<Vec<_>>();
+    equation_ranges.extend(region_eq_ranges);
+    if let Some(suffix_raw_start) = suffix_raw_start {
+        equation_ranges.extend(
+            cache
+                .equation_ranges
+                .iter()
+                .filter(|range| range.start >= suffix_raw_start)
+                .map(|range| shift_range(range, raw_delta)),
+        );
+    }
+    cache.equation_ranges = equation_ranges;
+
+    cache.raw = text.to_string();
+
+    context.index_mapper = cache.index_mapper.clone();
+    context.unstable_synth = cache.synth.clone();
+    context
+        .synth_source_mut(world)
+        .unwrap()
+        .replace(&cache.synth);
+    context.synth_cache = Some(cache);
+}
+
+/// Collects the top-level blocks of `nodes`, without emitting them.
+///
+/// A block is a run of nodes with no newline between them. It ends at the
+/// first newline of the node that terminates it.
+fn collect_blocks<'a>(
+    nodes: impl IntoIterator<Item = (Range<usize>, &'a SyntaxNode)>,
+    text: &str,
+) -> (Vec<AstBlock>, Vec<Range<usize>>) {
     let mut eq_ranges = Vec::new();
 
     let mut ast_blocks = Vec::<AstBlock>::new();
     let mut in_block = false;
 
-    let mut last_kind: Option<SyntaxKind> = None;
-
-    for node in children {
-        let range = world.range(node.span()).unwrap();
-
+    for (range, node) in nodes {
         if node.kind() == SyntaxKind::Equation {
             eq_ranges.push(range.clone());
         }
 
         if let Some(until_newline) = node.leaf_text().chars().position(|ch| ch == '\n') {
-            in_block = false;
-
-            if let Some(last_block) = ast_blocks.last_mut() {
+            if in_block && let Some(last_block) = ast_blocks.last_mut() {
                 last_block.range.end += until_newline;
-                wrap_block(&mut ir, text, last_block, last_kind, context);
             }
+
+            in_block = false;
+        } else if in_block {
+            let last_block = ast_blocks.last_mut().unwrap();
+            last_block.range.end = range.end;
+            last_block.kind = Some(node.kind());
         } else {
-            last_kind = Some(node.kind());
-
-            if in_block {
-                ast_blocks.last_mut().unwrap().range.end = range.end;
-            } else {
-                in_block = true;
-
-                context
-                    .index_mapper
-                    .push_raw_to_synth_unchecked(range.start, ir.len());
-                ast_blocks.push(AstBlock {
-                    range,
-                    is_inline: false,
-                });
-            }
+            in_block = true;
+
+            ast_blocks.push(AstBlock {
+                range,
+                is_inline: false,
+                kind: Some(node.kind()),
+                hash: 0,
+                synth_range: 0..0,
+            });
         }
     }
 
-    if let Some(last_block) = ast_blocks.last_mut()
-        && in_block
-    {
-        wrap_block(&mut ir, text, last_block, last_kind, context);
+    for block in &mut ast_blocks {
+        block.hash = FxBuildHasher.hash_one((&text[block.range.clone()], block.kind));
     }
 
-    // crate::log!("[RANGES]: {block_ranges:?}");
+    (ast_blocks, eq_ranges)
+}
 
-    // crate::log!(
-    //     "[SOURCE]:\n{}",
-    //     &ir[(state.prelude(id, RenderTarget::Svg) + prelude + "\n").len()..]
-    // );
+/// Emits `blocks` to `ir` in order, recording anchors in `index_mapper`.
+fn emit_blocks(
+    blocks: &mut [AstBlock],
+    text: &str,
+    ir: &mut String,
+    index_mapper: &mut IndexMapper,
+) {
+    for block in blocks {
+        block.synth_range = ir.len()..ir.len();
 
-    SynthResult {
-        synth: ir,
-        blocks: ast_blocks,
-        equation_ranges: eq_ranges,
+        index_mapper.push_raw_to_synth_unchecked(block.range.start, ir.len());
+        wrap_block(ir, text, block, index_mapper);
     }
 }
 
 /// Wraps a block of Typst source for rendering, updating the intermediate
 /// representation and block metadata.
 #[typst_macros::time]
-fn wrap_block(
-    ir: &mut String,
-    text: &str,
-    last_block: &mut AstBlock,
-    last_kind: Option<SyntaxKind>,
-    context: &mut SourceContext,
-) {
-    match last_kind {
+fn wrap_block(ir: &mut String, text: &str, block: &mut AstBlock, index_mapper: &mut IndexMapper) {
+    match block.kind {
         Some(
             SyntaxKind::LetBinding
             | SyntaxKind::SetRule
@@ -214,34 +507,194 @@ fn wrap_block(
             | SyntaxKind::LineComment
             | SyntaxKind::BlockComment,
         ) => {
-            *ir += &text[last_block.range.clone()];
+            *ir += &text[block.range.clone()];
         }
         Some(
             SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem | SyntaxKind::Label,
         ) => {
-            *ir += &text[last_block.range.clone()];
-            last_block.is_inline = true;
+            *ir += &text[block.range.clone()];
+            block.is_inline = true;
         }
         _ => {
             *ir += "#block(stroke:0pt,width:100%)[";
-            context
-                .index_mapper
-                .push_raw_to_synth_unchecked(last_block.range.start, ir.len());
-            *ir += &text[last_block.range.clone()];
-            context
-                .index_mapper
-                .push_raw_to_synth_unchecked(last_block.range.end, ir.len());
+            index_mapper.push_raw_to_synth_unchecked(block.range.start, ir.len());
+            *ir += &text[block.range.clone()];
+            index_mapper.push_raw_to_synth_unchecked(block.range.end, ir.len());
             *ir += "\n]";
 
-            last_block.is_inline = true;
+            block.is_inline = true;
         }
     }
 
-    context
-        .index_mapper
-        .push_raw_to_synth_unchecked(last_block.range.end, ir.len());
+    index_mapper.push_raw_to_synth_unchecked(block.range.end, ir.len());
     *ir += "\n";
-    context
-        .index_mapper
-        .push_raw_to_synth_unchecked(last_block.range.end, ir.len());
+    index_mapper.push_raw_to_synth_unchecked(block.range.end, ir.len());
+
+    block.synth_range.end = ir.len();
+}
+
+const fn shift_range(range: &Range<usize>, delta: isize) -> Range<usize> {
+    range.start.saturating_add_signed(delta)..range.end.saturating_add_signed(delta)
+}
+
+#[cfg(test)]
+fn synced_context(text: &str) -> (SourceContext, MnemoWorld) {
+    use typst_syntax::{FileId, RootedPath, VirtualPath, VirtualRoot};
+
+    let mut world = MnemoWorld::default();
+    let id = FileId::new(RootedPath::new(
+        VirtualRoot::Project,
+        VirtualPath::new("note.typ").unwrap(),
+    ));
+    let mut context = SourceContext::new(id, String::from("space"));
+    world.insert_source(context.raw_id, String::new());
+    world.insert_source(context.synth_id, String::new());
+
+    // Callers install the synth once it is built, as `TypstState` does.
+    let SynthResult { synth, .. } =
+        sync_source_context(text, String::from("#let prelude = 1\n"), &mut context, &mut world);
+    world.insert_source(context.synth_id, synth);
+
+    (context, world)
+}
+
+/// Applies edits to a note synced from `text` and checks that its raw text is
+/// `expected`, and that its synth and mapping equal a cold rebuild.
+#[cfg(test)]
+fn assert_edits(text: &str, edits: &[TypstTextEdit], expected: &str) {
+    let (mut context, mut world) = synced_context(text);
+    apply_source_edits(edits, &mut context, &mut world);
+    assert_eq!(context.raw_source(&world).unwrap().text(), expected);
+
+    let (cold, cold_world) = synced_context(expected);
+    let cache = context.synth_cache.as_ref().unwrap();
+    let cold_cache = cold.synth_cache.as_ref().unwrap();
+
+    assert_eq!(cache.raw, cold_cache.raw);
+    assert_eq!(cache.synth, cold_cache.synth);
+    assert_eq!(cache.blocks, cold_cache.blocks);
+    assert_eq!(cache.equation_ranges, cold_cache.equation_ranges);
+    assert_eq!(cache.index_mapper, cold_cache.index_mapper);
+    assert_eq!(context.index_mapper, cold.index_mapper);
+    assert_eq!(
+        context.synth_source(&world).unwrap().text(),
+        cold.synth_source(&cold_world).unwrap().text(),
+    );
+}
+
+#[cfg(test)]
+fn edit(range: Range<usize>, text: &str) -> TypstTextEdit {
+    TypstTextEdit {
+        range,
+        text: String::from(text),
+    }
+}
+
+#[test]
+fn test_apply_edits_insert_block() {
+    assert_edits(
+        "First\n\nSecond $x$\n",
+        &[edit(7..7, "Inserted *block*\n\n")],
+        "First\n\nInserted *block*\n\nSecond $x$\n",
+    );
+}
+
+#[test]
+fn test_apply_edits_delete_across_blocks() {
+    // Joins the end of the first paragraph with the start of the third.
+    assert_edits(
+        "First one\n\n= Heading\n\nThird one\n",
+        &[edit(5..28, "")],
+        "Firstone\n",
+    );
+    assert_edits(
+        "First one\n\n= Heading\n\nThird one\n",
+        &[edit(5..22, " ")],
+        "First Third one\n",
+    );
+}
+
+#[test]
+fn test_apply_edits_replace_across_blocks() {
+    assert_edits(
+        "A $a$\n\nB\n\n#let x = 1\nC #x\n",
+        &[edit(2..10, "`code`\n\n- item\n")],
+        "A `code`\n\n- item\n#let x = 1\nC #x\n",
+    );
+}
+
+#[test]
+fn test_apply_edits_in_sequence() {
+    // Each range is in the text left by the previous edit, in UTF-16.
+    assert_edits(
+        "Ünï 😀\n\nB\n",
+        &[edit(6..6, "!"), edit(0..1, "U"), edit(9..10, "C\n\nD")],
+        "Unï 😀!\n\nC\n\nD\n",
+    );
+}
+
+#[test]
+fn test_incremental_sync_matches_cold() {
+    const FRAGMENTS: [&str; 12] = [
+        "\n", "\n\n", "Text ", "$x^2$", "$ a + b $\n", "= Heading\n", "- item\n", "#let x = 1\n",
+        "#x ", "<label>", "*bold* ", "😀",
+    ];
+
+    // A xorshift generator, so failures are reproducible.
+    let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        usize::try_from(seed % bound as u64).unwrap()
+    };
+
+    let mut text = String::from("Intro $a$\n\n= Title\n\nBody #x\n- one\n- two\n");
+    let (mut context, mut world) = synced_context(&text);
+
+    for step in 0..300 {
+        let boundaries = text
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain([text.len()])
+            .collect::<Vec<_>>();
+        let start = boundaries[next(boundaries.len())];
+        let end = boundaries
+            .iter()
+            .copied()
+            .filter(|&idx| idx >= start)
+            .nth(next(8))
+            .unwrap_or(text.len());
+        let insert = if next(3) == 0 {
+            ""
+        } else {
+            FRAGMENTS[next(FRAGMENTS.len())]
+        };
+
+        if step % 2 == 0 {
+            // Edits through `applyEdits`, in UTF-16 code units.
+            let utf16 = |idx: usize| text[..idx].encode_utf16().count();
+            let edits = [edit(utf16(start)..utf16(end), insert)];
+            text.replace_range(start..end, insert);
+            apply_source_edits(&edits, &mut context, &mut world);
+        } else {
+            // Edits through a full sync of the new text.
+            text.replace_range(start..end, insert);
+            let prelude = context.synth_cache.as_ref().unwrap().prelude.clone();
+            let SynthResult { synth, .. } =
+                sync_source_context(&text, prelude, &mut context, &mut world);
+            context.synth_source_mut(&mut world).unwrap().replace(&synth);
+        }
+
+        let (cold, _) = synced_context(&text);
+        let cache = context.synth_cache.as_ref().unwrap();
+        let cold_cache = cold.synth_cache.as_ref().unwrap();
+
+        assert_eq!(cache.raw, text, "step {step}");
+        assert_eq!(cache.synth, cold_cache.synth, "step {step}: {text:?}");
+        assert_eq!(cache.blocks, cold_cache.blocks, "step {step}: {text:?}");
+        assert_eq!(cache.equation_ranges, cold_cache.equation_ranges, "step {step}: {text:?}");
+        assert_eq!(cache.index_mapper, cold_cache.index_mapper, "step {step}: {text:?}");
+        assert_eq!(context.synth_source(&world).unwrap().text(), cache.synth, "step {step}");
+    }
 }
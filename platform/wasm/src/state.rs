@@ -1,5 +1,5 @@
 use std::{
-    io::{Cursor, Read},
+    collections::BTreeMap,
     num::NonZeroUsize,
     path::PathBuf,
     str::FromStr,
@@ -9,36 +9,50 @@ use ecow::EcoVec;
 use indoc::formatdoc;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use tar::Archive;
+use time::{Date, Month, UtcOffset};
 use tsify::Tsify;
 use typst::{
-    compile,
-    ecow::EcoString,
-    foundations::Bytes,
+    World, compile,
+    ecow::{EcoString, eco_format},
+    foundations::{Bytes, CastInfo, Func, ParamInfo, Repr, Value},
     introspection::{HtmlPosition, PagedPosition},
     layout::{Abs, Point},
     syntax::{FileId, VirtualPath, package::PackageSpec},
+    text::{Font, FontInfo},
 };
 use typst_html::{HtmlDocument, HtmlOptions};
-use typst_ide::Tooltip;
+use typst_ide::{Definition, Tooltip};
 use typst_layout::PagedDocument;
-// use typst_html::html;
-// use typst_pdf::{PdfOptions, pdf};
-use typst_syntax::{LinkedNode, RootedPath, Side, Source, Tag, VirtualRoot};
+use typst_syntax::{LinkedNode, RootedPath, Side, Source, SyntaxKind, Tag, VirtualRoot};
 use wasm_bindgen::prelude::*;
 
 use crate::{
     bindings::{
-        CheckResult, CompileHTMLResult, CompilePagedResult, TypstCompletion, TypstDiagnostic,
-        TypstFileId, TypstHighlight, TypstJump,
+        CheckResult, CompileHTMLResult, CompilePagedResult, ExportHtmlResult, ExportPdfResult,
+        RenderPdfResult, TypstCompletion, TypstCursorJump, TypstDiagnostic, TypstFileId, TypstFont,
+        TypstFontCoverage, TypstFontFamily, TypstFontVariant, TypstHighlight, TypstInput, TypstJump,
+        TypstLocation, TypstPackage, TypstParameter, TypstSignatureHelp, TypstTextEdit,
     },
+    fonts,
+    outline::{self, TypstOutlineItem},
+    package::{self, LocalPackage, PackageArchive},
     renderer::{
+        export::{self, ExportNote},
         html::{self, RenderHtmlResult},
-        paged::svg::render_svgs_by_items,
-        recovery::remove_errornous_block,
+        paged::{
+            FrameBounds,
+            pdf::{self, PdfExportOptions},
+            svg::render_svgs_by_items,
+        },
+        recovery::{Recovery, recover},
     },
-    source::{RenderTarget, SourceContext, SpaceContext, SynthResult, sync_source_state},
+    source::{
+        RenderTarget, SourceContext, SpaceContext, SynthResult, apply_source_edits,
+        sync_source_state,
+    },
+    style::SpaceStyle,
     theme::ThemeColors,
+    utils::typst_string,
     world::MnemoWorld,
 };
 
@@ -71,6 +85,20 @@ impl TypstState {
         self.get_space_context_mut(id).theme = theme;
     }
 
+    /// Sets the typography of the note's space, which its prelude is
+    /// generated from.
+    #[wasm_bindgen(js_name = "setStyle")]
+    pub fn set_style(&mut self, id: &TypstFileId, style: SpaceStyle) {
+        self.get_space_context_mut(id).style = style;
+    }
+
+    /// Sets the project path of the source every note of the space imports,
+    /// like `spaces/<id>/lib.typ`, or removes it with `None`.
+    #[wasm_bindgen(js_name = "setSpaceLibrary")]
+    pub fn set_space_library(&mut self, id: &TypstFileId, path: Option<String>) {
+        self.get_space_context_mut(id).library = path;
+    }
+
     #[wasm_bindgen(js_name = "setFont")]
     pub fn set_font(&mut self, id: &TypstFileId, font: String) {
         self.get_space_context_mut(id).font = font;
@@ -91,6 +119,59 @@ impl TypstState {
         self.get_space_context_mut(id).locale = locale;
     }
 
+    /// Sets the date a note belongs to, as `YYYY-MM-DD`, which
+    /// `datetime.today()` then returns in it. `None` goes back to the current
+    /// date.
+    #[wasm_bindgen(js_name = "setDocumentDate")]
+    pub fn set_document_date(
+        &mut self,
+        id: &TypstFileId,
+        date: Option<String>,
+    ) -> Result<(), TypstError> {
+        let date = date.map(|date| parse_date(&date)).transpose()?;
+        self.get_source_context_mut(id).date = date;
+
+        Ok(())
+    }
+
+    /// Sets a space's UTC offset, in minutes east of UTC, which
+    /// `datetime.today()` uses in its notes. This is the negated value of
+    /// JavaScript's `Date.prototype.getTimezoneOffset()`.
+    #[wasm_bindgen(js_name = "setTimeZone")]
+    pub fn set_time_zone(&mut self, space_id: &str, offset_minutes: i32) -> Result<(), TypstError> {
+        let offset = offset_minutes
+            .checked_mul(60)
+            .and_then(|seconds| UtcOffset::from_whole_seconds(seconds).ok())
+            .ok_or_else(|| {
+                TypstError::new(
+                    TypstErrorKind::InvalidArgument,
+                    eco_format!("invalid UTC offset: {offset_minutes} minutes"),
+                )
+            })?;
+
+        self.world.utc_offsets.insert(space_id.to_string(), offset);
+
+        Ok(())
+    }
+
+    /// Sets the `sys.inputs` dictionary of a note, from a plain object of
+    /// JSON values. `null` or an empty object removes them.
+    #[wasm_bindgen(js_name = "setInputs")]
+    pub fn set_inputs(&mut self, id: &TypstFileId, inputs: JsValue) -> Result<(), TypstError> {
+        let inputs = serde_wasm_bindgen::from_value::<Option<BTreeMap<String, TypstInput>>>(inputs)
+            .map_err(|err| {
+                TypstError::new(
+                    TypstErrorKind::InvalidArgument,
+                    eco_format!("invalid inputs: {err}"),
+                )
+            })?;
+
+        self.world
+            .set_inputs(id.inner(), TypstInput::into_dict(inputs.unwrap_or_default()));
+
+        Ok(())
+    }
+
     #[wasm_bindgen(js_name = "createSourceId")]
     pub fn create_source_id(&mut self, path: &str, space_id: String) -> TypstFileId {
         let id = FileId::new(RootedPath::new(
@@ -105,8 +186,7 @@ impl TypstState {
         self.world.insert_source(source_ctx.raw_id, String::new());
         self.source_context_map.insert(id_wrapper, source_ctx);
 
-        let space_ctx = SpaceContext::new();
-        self.space_context_map.insert(space_id, space_ctx);
+        self.space_context_map.entry(space_id).or_default();
 
         id_wrapper
     }
@@ -137,39 +217,131 @@ impl TypstState {
         self.world.remove_source(&id.inner());
     }
 
-    #[wasm_bindgen(js_name = "installPackage")]
-    pub fn install_package(&mut self, spec: &str, data: Vec<u8>) -> Result<(), TypstError> {
-        let package_spec = Some(PackageSpec::from_str(spec).map_err(TypstError)?);
+    #[allow(clippy::needless_pass_by_value)]
+    #[wasm_bindgen(js_name = "applyEdits")]
+    pub fn apply_edits(
+        &mut self,
+        id: &TypstFileId,
+        edits: Vec<TypstTextEdit>,
+    ) -> Result<(), TypstError> {
+        let context = self.source_context_map.get_mut(id).ok_or_else(|| {
+            TypstError::new(
+                TypstErrorKind::UnknownSource,
+                "the note was not created with createSourceId",
+            )
+        })?;
 
-        let data = Cursor::new(data);
-        let data = flate2::read::GzDecoder::new(data);
-        let mut archive = Archive::new(data);
+        apply_source_edits(&edits, context, &mut self.world);
 
-        for entry in archive.entries().unwrap() {
-            let mut file = entry.unwrap();
-            let path = file.path().unwrap();
+        Ok(())
+    }
 
-            let root = match &package_spec {
-                Some(spec) => VirtualRoot::Package(spec.clone()),
-                None => VirtualRoot::Project,
-            };
+    /// Installs a package from its archive, returning a request for every
+    /// package that installed packages import but that is not installed yet,
+    /// so dependencies can be fetched before the next compile.
+    #[wasm_bindgen(js_name = "installPackage")]
+    pub fn install_package(
+        &mut self,
+        spec: &str,
+        data: &[u8],
+    ) -> Result<Vec<TypstRequest>, TypstError> {
+        let spec = parse_package_spec(spec)?;
 
-            let id = FileId::new(RootedPath::new(
-                root,
-                VirtualPath::new(path.to_str().expect("Invalid virtual path"))
-                    .expect("Invalid virtual path"),
-            ));
+        PackageArchive::unpack(&spec, data)?.install(&spec, &mut self.world)?;
 
-            let mut content = Vec::new();
-            file.read_to_end(&mut content).unwrap();
+        Ok(self
+            .world
+            .missing_dependencies()
+            .iter()
+            .map(TypstRequest::from)
+            .collect())
+    }
 
-            match String::from_utf8(content.clone()) {
-                Ok(content) => self.world.insert_source(id, content),
-                Err(..) => self.world.insert_file(id, Bytes::new(content)),
-            }
+    /// Registers the project files under `dir` as the package
+    /// `@local/<name>:<version>`, with a generated `typst.toml`.
+    ///
+    /// The package reads the project files as they are, so edits to them are
+    /// seen by the next compile. Returns requests for the entrypoint if it is
+    /// not loaded yet, and for packages it is missing.
+    #[wasm_bindgen(js_name = "registerLocalPackage")]
+    pub fn register_local_package(
+        &mut self,
+        name: &str,
+        version: &str,
+        dir: &str,
+        entrypoint: Option<String>,
+    ) -> Result<Vec<TypstRequest>, TypstError> {
+        let entrypoint = entrypoint.unwrap_or_else(|| String::from("lib.typ"));
+        let package = LocalPackage::new(name, version, dir, &entrypoint)?;
+        let entrypoint = package.file_id(&package.manifest.package.entrypoint);
+
+        self.world.register_local_package(package);
+
+        // Reading the entrypoint requests it if it is not loaded.
+        if let Some(entrypoint) = entrypoint {
+            let _ = self.world.source(entrypoint);
         }
 
-        Ok(())
+        let mut requests = self.process_requests();
+        requests.extend(self.world.missing_dependencies().iter().map(TypstRequest::from));
+
+        Ok(requests)
+    }
+
+    /// Removes an installed package, returning whether it was installed.
+    #[wasm_bindgen(js_name = "uninstallPackage")]
+    pub fn uninstall_package(&mut self, spec: &str) -> Result<bool, TypstError> {
+        let spec = parse_package_spec(spec)?;
+
+        Ok(self.world.uninstall_package(&spec))
+    }
+
+    /// Writes one archive with the installed packages `specs` and everything
+    /// they depend on, for `importPackageBundle` on another device.
+    #[wasm_bindgen(js_name = "exportPackageBundle")]
+    pub fn export_package_bundle(&self, specs: Vec<String>) -> Result<Vec<u8>, TypstError> {
+        let specs = specs
+            .into_iter()
+            .map(|spec| parse_package_spec(&spec))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        package::export_bundle(&specs, &self.world)
+    }
+
+    /// Installs every package of a bundle from `exportPackageBundle`,
+    /// returning the installed packages.
+    #[wasm_bindgen(js_name = "importPackageBundle")]
+    pub fn import_package_bundle(
+        &mut self,
+        data: &[u8],
+    ) -> Result<Vec<TypstPackage>, TypstError> {
+        let specs = package::import_bundle(data, &mut self.world)?;
+
+        Ok(specs
+            .iter()
+            .filter_map(|spec| {
+                let manifest = self.world.packages.get(spec)?;
+
+                Some(TypstPackage::new(spec, manifest))
+            })
+            .collect())
+    }
+
+    /// Lists the installed packages.
+    #[wasm_bindgen(js_name = "listPackages")]
+    pub fn list_packages(&self) -> Vec<TypstPackage> {
+        let mut packages = self
+            .world
+            .packages
+            .iter()
+            .map(|(spec, manifest)| TypstPackage::new(spec, manifest))
+            .collect::<Vec<_>>();
+
+        packages.sort_by(|a, b| {
+            (&a.namespace, &a.name, &a.version).cmp(&(&b.namespace, &b.name, &b.version))
+        });
+
+        packages
     }
 
     #[wasm_bindgen(js_name = "installFont")]
@@ -177,6 +349,109 @@ impl TypstState {
         self.world.install_font(bytes);
     }
 
+    /// Describes every face in a font file, so the app can keep the result
+    /// and later register the faces with `registerFonts` without loading the
+    /// file.
+    #[wasm_bindgen(js_name = "fontIndex")]
+    #[must_use]
+    pub fn font_index(bytes: Vec<u8>) -> JsValue {
+        let infos = Font::iter(Bytes::new(bytes))
+            .map(|font| font.info().clone())
+            .collect::<Vec<_>>();
+
+        serde_wasm_bindgen::to_value(&infos).unwrap_or(JsValue::NULL)
+    }
+
+    /// Registers faces described by `fontIndex` without loading them, for a
+    /// single space or, without one, for all spaces.
+    ///
+    /// When a compile selects one of these faces, it is reported as a `font`
+    /// request and should be installed with `installFont` or
+    /// `installSpaceFont`.
+    #[allow(clippy::needless_pass_by_value)]
+    #[wasm_bindgen(js_name = "registerFonts")]
+    pub fn register_fonts(
+        &mut self,
+        index: JsValue,
+        space_id: Option<String>,
+    ) -> Result<(), TypstError> {
+        let infos = serde_wasm_bindgen::from_value::<Vec<FontInfo>>(index)
+            .map_err(|err| {
+                TypstError::new(
+                    TypstErrorKind::InvalidArgument,
+                    eco_format!("invalid font index: {err}"),
+                )
+            })?;
+
+        self.world.register_fonts(space_id.as_deref(), infos);
+
+        Ok(())
+    }
+
+    /// Installs a font for the notes of a single space only.
+    #[wasm_bindgen(js_name = "installSpaceFont")]
+    pub fn install_space_font(&mut self, space_id: &str, bytes: Vec<u8>) {
+        self.world.install_space_font(space_id, bytes);
+    }
+
+    /// Removes a family installed with `installSpaceFont`, returning whether
+    /// any faces were removed.
+    #[wasm_bindgen(js_name = "uninstallFont")]
+    pub fn uninstall_font(&mut self, space_id: &str, family: &str) -> bool {
+        self.world.uninstall_space_font(space_id, family)
+    }
+
+    /// Lists the font families the notes of a space can use.
+    #[wasm_bindgen(js_name = "listFonts")]
+    pub fn list_fonts(&self, space_id: &str) -> Vec<TypstFont> {
+        let font_loader = self.world.font_loader();
+
+        let shared = font_loader.shared.slots.iter().map(|slot| (slot, true));
+        let own = font_loader
+            .space_fonts(space_id)
+            .iter()
+            .map(|slot| (slot, false));
+
+        let mut fonts = Vec::<TypstFont>::new();
+
+        for (slot, shared) in shared.chain(own) {
+            let family = &slot.info.family;
+
+            if !fonts.iter().any(|font| &font.family == family) {
+                fonts.push(TypstFont {
+                    family: family.clone(),
+                    shared,
+                });
+            }
+        }
+
+        fonts
+    }
+
+    /// Describes the font families available to a space, or to every space
+    /// when `space_id` is not given.
+    #[allow(clippy::needless_pass_by_value)]
+    #[wasm_bindgen(js_name = "listFontFamilies")]
+    pub fn list_font_families(&self, space_id: Option<String>) -> Vec<TypstFontFamily> {
+        fonts::font_families(&self.world.font_loader().set(space_id.as_deref()).book)
+    }
+
+    /// Reports the characters of a note that its space's text, math and code
+    /// families have no glyph for.
+    #[wasm_bindgen(js_name = "checkFontCoverage")]
+    pub fn check_font_coverage(&self, id: &TypstFileId) -> TypstFontCoverage {
+        let context = self.get_source_context(id);
+        let space_ctx = self.get_space_context(id);
+
+        let Some(raw_source) = context.raw_source(&self.world) else {
+            return TypstFontCoverage::default();
+        };
+
+        let book = &self.world.font_loader().set(Some(&context.space_id)).book;
+
+        fonts::font_coverage(raw_source, space_ctx, book)
+    }
+
     fn process_requests(&self) -> Vec<TypstRequest> {
         let mut requests = Vec::new();
 
@@ -190,16 +465,20 @@ impl TypstState {
             false
         });
 
-        self.world.requested_packages.retain(|package| {
-            requests.push(TypstRequest::Package {
-                namespace: package.namespace.to_string(),
-                name: package.name.to_string(),
-                version: package.version.to_string(),
+        self.world.requested_fonts.retain(|(family, variant)| {
+            requests.push(TypstRequest::Font {
+                family: family.clone(),
+                variant: TypstFontVariant::from(*variant),
             });
 
             false
         });
 
+        self.world.requested_packages.retain(|package| {
+            requests.push(TypstRequest::from(package));
+            false
+        });
+
         requests
     }
 
@@ -215,7 +494,9 @@ impl TypstState {
         CompilePagedResult {
             frames: result.frames,
             tooltips: result.tooltips,
+            pages: result.pages,
             diagnostics: result.diagnostics,
+            recovered: result.recovered,
             requests: self.process_requests(),
         }
     }
@@ -232,6 +513,7 @@ impl TypstState {
         CompileHTMLResult {
             frames: result.frames,
             diagnostics: result.diagnostics,
+            recovered: result.recovered,
             requests: self.process_requests(),
         }
     }
@@ -389,27 +671,21 @@ impl TypstState {
         highlights
     }
 
+    /// Jumps to the source of a click at `(x, y)` on the page at index `page`.
     #[wasm_bindgen(js_name = "jumpPaged")]
-    pub fn jump_paged(&mut self, id: &TypstFileId, x: f64, mut y: f64) -> Option<TypstJump> {
+    pub fn jump_paged(
+        &mut self,
+        id: &TypstFileId,
+        page: usize,
+        x: f64,
+        y: f64,
+    ) -> Option<TypstJump> {
         let context = self.source_context_map.get(id)?;
         let document = context.paged_document.as_ref()?;
-
-        let index = document
-            .pages()
-            .iter()
-            .rposition(|page| y >= page.frame.height().to_pt())
-            .unwrap_or_default();
-
-        let page_offset = document
-            .pages()
-            .iter()
-            .map(|page| page.frame.height().to_pt())
-            .rfind(|height| y >= *height)
-            .unwrap_or_default();
-        y -= page_offset;
+        context.enter(&mut self.world);
 
         let position = PagedPosition {
-            page: NonZeroUsize::new(index + 1).unwrap(),
+            page: NonZeroUsize::new(page + 1)?,
             point: Point::new(Abs::pt(x), Abs::pt(y)),
         };
 
@@ -421,6 +697,7 @@ impl TypstState {
     pub fn jump_html(&mut self, id: &TypstFileId, element: Vec<usize>) -> Option<TypstJump> {
         let context = self.source_context_map.get(id)?;
         let document = context.html_document.as_ref()?;
+        context.enter(&mut self.world);
 
         typst_ide::jump_from_click(
             &self.world,
@@ -430,6 +707,48 @@ impl TypstState {
         .and_then(|jump| TypstJump::from_mapped(jump, context, &self.world))
     }
 
+    /// Finds where the raw cursor is rendered, so the preview can follow it.
+    ///
+    /// Uses the paged document when one was compiled, otherwise the HTML
+    /// document.
+    #[wasm_bindgen(js_name = "jumpFromCursor")]
+    pub fn jump_from_cursor(
+        &mut self,
+        id: &TypstFileId,
+        raw_cursor_utf16: usize,
+    ) -> Option<TypstCursorJump> {
+        let context = self.source_context_map.get(id)?;
+        context.enter(&mut self.world);
+
+        let synth_source = context.synth_source(&self.world)?;
+        let raw_source = context.raw_source(&self.world)?;
+
+        let raw_cursor = raw_source.lines().utf16_to_byte(raw_cursor_utf16)?;
+        let synth_cursor = context.map_raw_to_synth_from_left(raw_cursor);
+
+        if let Some(document) = &context.paged_document {
+            let position = typst_ide::jump_from_cursor(document, synth_source, synth_cursor)
+                .into_iter()
+                .next()?;
+            let (frame_index, x, y) = FrameBounds::locate(
+                &context.frame_bounds,
+                position.page.get() - 1,
+                position.point,
+            )?;
+
+            return Some(TypstCursorJump::Paged { frame_index, x, y });
+        }
+
+        let document = context.html_document.as_ref()?;
+        let position = typst_ide::jump_from_cursor(document, synth_source, synth_cursor)
+            .into_iter()
+            .next()?;
+
+        Some(TypstCursorJump::Html {
+            element: position.element().collect(),
+        })
+    }
+
     #[wasm_bindgen]
     pub fn autocomplete(
         &mut self,
@@ -438,6 +757,7 @@ impl TypstState {
         explicit: bool,
     ) -> Option<Autocomplete> {
         let context = self.source_context_map.get(id)?;
+        context.enter(&mut self.world);
 
         let raw_source = context.raw_source(&self.world)?;
         // let synth_source = context.synth_source(&self.world)?;
@@ -472,8 +792,14 @@ impl TypstState {
     }
 
     #[wasm_bindgen]
-    pub fn hover(&self, id: &TypstFileId, raw_cursor_utf16: usize, side: i8) -> Option<String> {
-        let context = self.source_context_map.get(id).unwrap();
+    pub fn hover(
+        &mut self,
+        id: &TypstFileId,
+        raw_cursor_utf16: usize,
+        side: i8,
+    ) -> Option<String> {
+        let context = self.source_context_map.get(id)?;
+        context.enter(&mut self.world);
 
         let synth_source = context.synth_source(&self.world)?;
         let raw_source = context.raw_source(&self.world)?;
@@ -504,6 +830,209 @@ impl TypstState {
         })
     }
 
+    /// Finds where the symbol or label under the cursor is defined.
+    #[wasm_bindgen]
+    pub fn definition(
+        &mut self,
+        id: &TypstFileId,
+        raw_cursor_utf16: usize,
+    ) -> Option<TypstLocation> {
+        let context = self.source_context_map.get(id)?;
+        context.enter(&mut self.world);
+
+        let synth_source = context.synth_source(&self.world)?;
+        let raw_source = context.raw_source(&self.world)?;
+
+        let raw_cursor = raw_source.lines().utf16_to_byte(raw_cursor_utf16)?;
+        let synth_cursor = context.map_raw_to_synth_from_right(raw_cursor);
+
+        let definition = typst_ide::definition(
+            &self.world,
+            context.paged_document.as_ref(),
+            synth_source,
+            synth_cursor,
+            Side::After,
+        )?;
+
+        TypstLocation::from_definition(definition, context, &self.world)
+    }
+
+    /// Finds every use of the symbol or label under the cursor in the note,
+    /// starting with its definition.
+    ///
+    /// Identifiers are matched by resolving each one with the same name to its
+    /// definition, so shadowed bindings are told apart. Labels are matched by
+    /// name across `<label>`s and `@ref`s.
+    #[wasm_bindgen]
+    pub fn references(&mut self, id: &TypstFileId, raw_cursor_utf16: usize) -> Vec<TypstLocation> {
+        let Some(context) = self.source_context_map.get(id) else {
+            return Vec::new();
+        };
+        context.enter(&mut self.world);
+        let (Some(synth_source), Some(raw_source)) = (
+            context.synth_source(&self.world),
+            context.raw_source(&self.world),
+        ) else {
+            return Vec::new();
+        };
+
+        let Some(raw_cursor) = raw_source.lines().utf16_to_byte(raw_cursor_utf16) else {
+            return Vec::new();
+        };
+        let synth_cursor = context.map_raw_to_synth_from_right(raw_cursor);
+
+        let root = LinkedNode::new(synth_source.root());
+        let Some(leaf) = root.leaf_at(synth_cursor, Side::After) else {
+            return Vec::new();
+        };
+
+        let definition_at = |node: &LinkedNode| {
+            match typst_ide::definition(
+                &self.world,
+                context.paged_document.as_ref(),
+                synth_source,
+                node.offset(),
+                Side::After,
+            ) {
+                Some(Definition::Span(span)) => Some(span),
+                _ => None,
+            }
+        };
+
+        let label = label_name(&leaf);
+        let target = if label.is_some() {
+            None
+        } else if matches!(leaf.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent) {
+            let Some(target) = definition_at(&leaf) else {
+                return Vec::new();
+            };
+
+            Some(target)
+        } else {
+            return Vec::new();
+        };
+
+        let mut uses = Vec::new();
+        let mut queue = vec![root];
+
+        while let Some(curr) = queue.pop() {
+            let is_use = match (label, target) {
+                (Some(label), _) => label_name(&curr) == Some(label),
+                (None, Some(target)) => {
+                    matches!(curr.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent)
+                        && curr.leaf_text() == leaf.leaf_text()
+                        && definition_at(&curr) == Some(target)
+                }
+                (None, None) => false,
+            };
+
+            if is_use && !context.in_prelude(curr.offset()) {
+                uses.push(curr.span());
+            }
+
+            for child in curr.children() {
+                queue.push(child);
+            }
+        }
+
+        let mut uses = uses
+            .into_iter()
+            .filter_map(|span| TypstLocation::from_span(span, context, &self.world))
+            .collect::<Vec<_>>();
+        uses.sort_by_key(|location| {
+            match location {
+                TypstLocation::Raw { range } => range.start,
+                _ => usize::MAX,
+            }
+        });
+
+        let mut locations = target
+            .and_then(|target| TypstLocation::from_span(target, context, &self.world))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        for location in uses {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+
+        locations
+    }
+
+    /// Describes the function call whose arguments enclose the cursor.
+    ///
+    /// The callee is resolved like `typst_ide` does, from the values traced
+    /// through the last compile, falling back to the standard library's scope
+    /// for plain identifiers.
+    #[wasm_bindgen(js_name = "signatureHelp")]
+    pub fn signature_help(
+        &mut self,
+        id: &TypstFileId,
+        raw_cursor_utf16: usize,
+    ) -> Option<TypstSignatureHelp> {
+        let context = self.source_context_map.get(id)?;
+        context.enter(&mut self.world);
+
+        let raw_source = context.raw_source(&self.world)?;
+        let synth_source = &Source::new(id.inner(), context.unstable_synth.clone());
+
+        let raw_cursor = raw_source.lines().utf16_to_byte(raw_cursor_utf16)?;
+        let synth_cursor = context.map_raw_to_synth_from_left(raw_cursor);
+
+        let root = LinkedNode::new(synth_source.root());
+        let leaf = root.leaf_at(synth_cursor, Side::Before)?;
+
+        let (callee, args) = enclosing_call(leaf, synth_cursor)?;
+        let func = self.resolve_func(&callee)?;
+
+        let params = func.params().collect::<Vec<_>>();
+        let active_param = active_param(&args, synth_cursor, &params);
+
+        let params = params
+            .iter()
+            .map(|param| {
+                TypstParameter {
+                    label: param_label(param),
+                    docs: param
+                        .to_native()
+                        .map_or_else(String::new, |info| info.docs.to_string()),
+                    positional: param.positional(),
+                    named: param.named(),
+                    required: param.required(),
+                    variadic: param.variadic(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let label = format!(
+            "{name}({params})",
+            name = func.name().unwrap_or_else(|| callee.leaf_text().as_str()),
+            params = params
+                .iter()
+                .map(|param| param.label.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+
+        Some(TypstSignatureHelp {
+            label,
+            docs: func.docs().map(str::to_string),
+            params,
+            active_param,
+        })
+    }
+
+    /// Lists the note's headings, labels, figures and labelled equations as a
+    /// tree nested by heading level.
+    #[wasm_bindgen]
+    pub fn outline(&self, id: &TypstFileId) -> Vec<TypstOutlineItem> {
+        self.source_context_map
+            .get(id)
+            .map(|context| outline::outline(context, &self.world))
+            .unwrap_or_default()
+    }
+
     #[wasm_bindgen]
     pub fn resize(&mut self, id: &TypstFileId, width: Option<f64>, height: Option<f64>) -> bool {
         let context = self.source_context_map.get_mut(id).unwrap();
@@ -519,60 +1048,76 @@ impl TypstState {
         width_changed
     }
 
-    // #[wasm_bindgen(js_name = renderPdf)]
-    // pub fn render_pdf(&mut self, id: &TypstFileId) -> RenderPdfResult {
-    //     self.world.synth_id = Some(id.inner());
-
-    //     let mut ir = self.prelude(id, RenderTarget::Pdf);
-
-    //     let context = self.source_context_map.get_mut(id).unwrap();
-    //     let synth_source = context.synth_source_mut(&mut self.world).unwrap();
-    //     let text = synth_source.text().to_string();
-    //     ir += &text;
-
-    //     synth_source.replace(&ir);
-
-    //     self.world.insert_source(context.raw_id, text);
-    //     self.world.raw_id = Some(context.raw_id);
-
-    //     let compiled = compile(&self.world);
-    //     let mut diagnostics =
-    //         TypstDiagnostic::from_diagnostics(compiled.warnings, context,
-    // &self.world).into_vec();
-
-    //     let bytes = match compiled.output {
-    //         Ok(document) => {
-    //             match pdf(&document, &PdfOptions::default()) {
-    //                 Ok(pdf) => Some(pdf),
-    //                 Err(source_diagnostics) => {
-    //                     diagnostics.extend(TypstDiagnostic::from_diagnostics(
-    //                         source_diagnostics,
-    //                         context,
-    //                         &self.world,
-    //                     ));
-
-    //                     None
-    //                 }
-    //             }
-    //         }
-    //         Err(source_diagnostics) => {
-    //             diagnostics.extend(TypstDiagnostic::from_diagnostics(
-    //                 source_diagnostics,
-    //                 context,
-    //                 &self.world,
-    //             ));
-
-    //             None
-    //         }
-    //     };
-
-    //     RenderPdfResult { bytes, diagnostics }
-    // }
+    /// Lays the note out on real pages with the given setup, as a print
+    /// preview of its PDF export. Pass `None` to go back to a single page that
+    /// grows with the content.
+    #[wasm_bindgen(js_name = "setPageMode")]
+    pub fn set_page_mode(&mut self, id: &TypstFileId, options: Option<PdfExportOptions>) {
+        let context = self.get_source_context_mut(id);
+
+        context.page_mode = options.is_some();
+        if let Some(options) = options {
+            context.pdf_options = options;
+        }
+    }
+
+    #[wasm_bindgen(js_name = "renderPdf")]
+    pub fn render_pdf(
+        &mut self,
+        id: &TypstFileId,
+        text: &str,
+        prelude: &str,
+        options: PdfExportOptions,
+    ) -> RenderPdfResult {
+        let result = pdf::render_pdf(id, text, prelude, options, self);
+
+        RenderPdfResult {
+            bytes: result.bytes,
+            diagnostics: result.diagnostics,
+            requests: self.process_requests(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "exportPdf")]
+    pub fn export_pdf(
+        &mut self,
+        id: &TypstFileId,
+        notes: Vec<ExportNote>,
+        prelude: &str,
+        options: PdfExportOptions,
+    ) -> Result<ExportPdfResult, TypstError> {
+        let result = export::export_pdf(id, notes, prelude, options, self)?;
+
+        Ok(ExportPdfResult {
+            bytes: result.bytes,
+            diagnostics: result.diagnostics,
+            requests: self.process_requests(),
+        })
+    }
+
+    #[wasm_bindgen(js_name = "exportHTML")]
+    pub fn export_html(
+        &mut self,
+        id: &TypstFileId,
+        notes: Vec<ExportNote>,
+        prelude: &str,
+    ) -> Result<ExportHtmlResult, TypstError> {
+        let result = export::export_html(id, notes, prelude, self)?;
+
+        Ok(ExportHtmlResult {
+            html: result.html,
+            diagnostics: result.diagnostics,
+            requests: self.process_requests(),
+        })
+    }
 
     #[wasm_bindgen(js_name = renderHtml)]
     pub fn render_html(&mut self, id: &TypstFileId, text: &str, prelude: &str) -> RenderHtmlResult {
-        let SynthResult { synth, blocks, .. } =
-            sync_source_state(id, text, prelude, RenderTarget::Html, self);
+        let SynthResult {
+            synth,
+            mut blocks,
+            equation_ranges,
+        } = sync_source_state(id, text, prelude, RenderTarget::Html, self);
 
         let mut diagnostics = Vec::new();
         let mut compiled_warnings = None;
@@ -585,8 +1130,8 @@ impl TypstState {
             .replace(&synth);
         context.unstable_synth = synth;
 
+        let mut recovery = Recovery::default();
         let mut document = None;
-        let mut convergence = 0_u8;
 
         while document.is_none() {
             let compiled = compile::<HtmlDocument>(&self.world);
@@ -612,13 +1157,6 @@ impl TypstState {
                     }
                 }
                 Err(source_diagnostics) => {
-                    convergence += 1;
-                    if convergence >= 128 {
-                        crate::error!("COULD NOT CONVERGE ‼️");
-
-                        break;
-                    }
-
                     diagnostics.extend(TypstDiagnostic::from_diagnostics(
                         source_diagnostics.clone(),
                         context,
@@ -627,16 +1165,16 @@ impl TypstState {
 
                     crate::error!("[ERRORS]: {diagnostics:?}");
 
-                    let indicies = remove_errornous_block(
-                        &blocks,
+                    if recover(
+                        &mut recovery,
                         &source_diagnostics,
+                        &mut blocks,
+                        &equation_ranges,
                         context,
                         &mut self.world,
-                    );
-
-                    if indicies.is_empty() {
-                        crate::error!("NO ERROR BLOCKS FOUND ‼️");
-
+                    )
+                    .is_none()
+                    {
                         break;
                     }
 
@@ -669,6 +1207,31 @@ impl TypstState {
         &mut self.world
     }
 
+    fn resolve_func(&self, callee: &LinkedNode) -> Option<Func> {
+        let traced = typst_ide::analyze_expr(&self.world, callee)
+            .into_iter()
+            .find_map(|(value, _)| {
+                match value {
+                    Value::Func(func) => Some(func),
+                    _ => None,
+                }
+            });
+
+        traced.or_else(|| {
+            let library = self.world.library();
+            let scope = match callee.kind() {
+                SyntaxKind::Ident => library.global.scope(),
+                SyntaxKind::MathIdent => library.math.scope(),
+                _ => return None,
+            };
+
+            match scope.get(callee.leaf_text())?.read() {
+                Value::Func(func) => Some(func.clone()),
+                _ => None,
+            }
+        })
+    }
+
     pub fn get_source_context(&self, id: &TypstFileId) -> &SourceContext {
         self.source_context_map.get(id).unwrap()
     }
@@ -695,42 +1258,57 @@ impl TypstState {
         let space_ctx = self.space_context_map.get(&source_ctx.space_id).unwrap();
 
         let page_config = match render_target {
-            RenderTarget::Svg => {
+            RenderTarget::Svg if !source_ctx.page_mode => {
                 formatdoc!(
-                    r#"
+                    r"
                         #set page(fill:rgb(0,0,0,0),width:{width},height:auto,margin:0pt)
-                        #set text(top-edge:"ascender",bottom-edge:"descender")
-                        #set par(leading:0.125em)
-                    "#,
+                    ",
                     width = source_ctx.width,
                 )
             }
-            RenderTarget::Pdf => {
+            RenderTarget::Svg | RenderTarget::Pdf => {
+                let options = &source_ctx.pdf_options;
+
+                let size = match &options.paper {
+                    Some(paper) => format!("paper:{}", typst_string(paper)),
+                    None => format!("width:{width},height:auto", width = source_ctx.width),
+                };
+                let fill = if options.fill {
+                    "theme.background"
+                } else {
+                    "none"
+                };
+
                 formatdoc!(
                     r"
-                        #set page(width:{width},height:auto,margin:16pt)
+                        #set page({size},margin:{margin},fill:{fill})
                     ",
-                    width = source_ctx.width,
+                    margin = options.margin,
                 )
             }
             RenderTarget::Html => formatdoc!(""),
         };
 
+        // The editor's text metrics, shared by the flowing and the paged view.
+        let text_config = match render_target {
+            RenderTarget::Svg => {
+                formatdoc!(
+                    r#"
+                        #set text(top-edge:"ascender",bottom-edge:"descender")
+                        #set par(leading:{leading}em)
+                    "#,
+                    leading = space_ctx.style.leading,
+                )
+            }
+            RenderTarget::Pdf | RenderTarget::Html => String::new(),
+        };
+
         formatdoc!(
             r#"
                 #let theme={theme}
                 #set text(fill:theme.on-background,size:{text_size}pt,lang:"{locale}",font:"{font}")
 
-                #show heading.where(level:1):set text(fill:theme.primary,size:32pt,weight:400)
-                #show heading.where(level:2):set text(fill:theme.secondary,size:28pt,weight:400)
-                #show heading.where(level:3):set text(fill:theme.tertiary,size:24pt,weight:400)
-                #show heading.where(level:4):set text(fill:theme.primary,size:22pt,weight:400)
-                #show heading.where(level:5):set text(fill:theme.secondary,size:16pt,weight:500)
-                #show heading.where(level:6):set text(fill:theme.tertiary,size:14pt,weight:500)
-
-                #show link:set text(fill:theme.primary)
-                #show link:underline
-
+                {style}
                 #set line(stroke:theme.outline)
                 #set table(stroke:theme.outline)
                 #set circle(stroke:theme.outline)
@@ -742,14 +1320,14 @@ impl TypstState {
                 #set square(stroke:theme.outline)
 
                 #show math.equation:set text(font:"{math_font}")
-                #show math.equation.where(block:true):set text(size:18pt)
-                #show math.equation.where(block:true):set par(leading:9pt)
 
                 #show raw:set text(font:"{code_font}")
 
                 #context {{show math.equation:set text(size:text.size*2)}}
 
                 {page_config}
+                {text_config}
+                {library}
             "#,
             text_size = source_ctx.text_size,
             font = space_ctx.font,
@@ -757,10 +1335,131 @@ impl TypstState {
             code_font = space_ctx.code_font.as_ref().unwrap_or(&space_ctx.font),
             locale = space_ctx.locale,
             theme = space_ctx.theme,
+            style = space_ctx.style,
+            library = space_ctx.library.as_ref().map_or_else(String::new, |path| {
+                let path = format!("/{}", path.trim_start_matches('/'));
+                format!("#import {}:*", typst_string(&path))
+            }),
         )
     }
 }
 
+/// Finds the innermost call whose parentheses enclose `cursor`, returning its
+/// callee and argument list.
+fn enclosing_call(leaf: LinkedNode, cursor: usize) -> Option<(LinkedNode, LinkedNode)> {
+    let mut node = Some(leaf);
+
+    while let Some(curr) = node {
+        if curr.kind() == SyntaxKind::FuncCall
+            && let Some(args) = curr.children().find(|child| child.kind() == SyntaxKind::Args)
+        {
+            let left_paren = args
+                .children()
+                .find(|child| child.kind() == SyntaxKind::LeftParen);
+            let right_paren = args
+                .children()
+                .find(|child| child.kind() == SyntaxKind::RightParen);
+
+            let in_parens = left_paren.is_some_and(|paren| paren.range().end <= cursor)
+                && right_paren.is_none_or(|paren| cursor <= paren.offset());
+
+            if in_parens {
+                let callee = curr.children().next()?;
+
+                return Some((callee, args));
+            }
+        }
+
+        node = curr.parent().cloned();
+    }
+
+    None
+}
+
+/// Finds the parameter that the argument at `cursor` is passed to.
+fn active_param(args: &LinkedNode, cursor: usize, params: &[ParamInfo]) -> Option<usize> {
+    let mut positional = 0;
+    let mut named = None;
+    let mut in_positional = false;
+
+    for child in args.children() {
+        if child.offset() >= cursor {
+            break;
+        }
+
+        match child.kind() {
+            SyntaxKind::Comma => {
+                if in_positional {
+                    positional += 1;
+                }
+
+                named = None;
+                in_positional = false;
+            }
+            SyntaxKind::Named => {
+                named = child.children().next().map(|name| name.leaf_text().clone());
+            }
+            SyntaxKind::LeftParen | SyntaxKind::RightParen | SyntaxKind::Spread => {}
+            kind if kind.is_trivia() => {}
+            _ => in_positional = true,
+        }
+    }
+
+    if let Some(named) = named {
+        return params
+            .iter()
+            .position(|param| param.name() == Some(named.as_str()));
+    }
+
+    let mut positional_params = params
+        .iter()
+        .enumerate()
+        .filter(|(_, param)| param.positional());
+
+    positional_params
+        .clone()
+        .nth(positional)
+        .or_else(|| positional_params.find(|(_, param)| param.variadic()))
+        .map(|(idx, _)| idx)
+}
+
+/// A parameter as `name: type`, like `..children: content` or
+/// `dir: direction | auto`. Closure parameters have no declared type and are
+/// shown by name only.
+fn param_label(param: &ParamInfo) -> String {
+    let prefix = if param.variadic() { ".." } else { "" };
+    let name = param.name().unwrap_or("_");
+
+    let Some(info) = param.to_native() else {
+        return format!("{prefix}{name}");
+    };
+
+    let mut types = Vec::new();
+    info.input.walk(|cast| {
+        let ty = match cast {
+            CastInfo::Any => EcoString::from("any"),
+            CastInfo::Value(value, _) => value.repr(),
+            CastInfo::Type(ty) => ty.short_name().into(),
+            CastInfo::Union(_) => return,
+        };
+
+        if !types.contains(&ty) {
+            types.push(ty);
+        }
+    });
+
+    format!("{prefix}{name}: {}", types.join(" | "))
+}
+
+/// The name of a `<label>` or `@ref` leaf.
+fn label_name<'a>(node: &'a LinkedNode) -> Option<&'a str> {
+    match node.kind() {
+        SyntaxKind::Label => node.leaf_text().strip_prefix('<')?.strip_suffix('>'),
+        SyntaxKind::RefMarker => node.leaf_text().strip_prefix('@'),
+        _ => None,
+    }
+}
+
 #[derive(Tsify, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 #[serde(tag = "type", content = "value", rename_all = "kebab-case")]
@@ -772,11 +1471,92 @@ pub enum TypstRequest {
         name: String,
         version: String,
     },
+    /// A registered font face that was selected but is not loaded yet.
+    Font {
+        family: String,
+        variant: TypstFontVariant,
+    },
 }
 
-#[derive(Tsify, Serialize, Deserialize)]
+impl From<&PackageSpec> for TypstRequest {
+    fn from(spec: &PackageSpec) -> Self {
+        Self::Package {
+            namespace: spec.namespace.to_string(),
+            name: spec.name.to_string(),
+            version: spec.version.to_string(),
+        }
+    }
+}
+
+fn parse_package_spec(spec: &str) -> Result<PackageSpec, TypstError> {
+    PackageSpec::from_str(spec)
+        .map_err(|err| TypstError::new(TypstErrorKind::InvalidArgument, err))
+}
+
+fn parse_date(date: &str) -> Result<Date, TypstError> {
+    let invalid = || {
+        TypstError::new(
+            TypstErrorKind::InvalidArgument,
+            eco_format!("invalid date: {date}"),
+        )
+    };
+
+    let mut parts = date.split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+
+    let year = year.parse().map_err(|_| invalid())?;
+    let month = month
+        .parse::<u8>()
+        .ok()
+        .and_then(|month| Month::try_from(month).ok())
+        .ok_or_else(invalid)?;
+    let day = day.parse().map_err(|_| invalid())?;
+
+    Date::from_calendar_date(year, month, day).map_err(|_| invalid())
+}
+
+/// An error returned to JavaScript, with a kind the app can match on.
+#[derive(Tsify, Serialize, Deserialize, Debug)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct TypstError(EcoString);
+pub struct TypstError {
+    pub kind: TypstErrorKind,
+    pub message: EcoString,
+}
+
+impl TypstError {
+    pub fn new(kind: TypstErrorKind, message: impl Into<EcoString>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypstErrorKind {
+    /// An argument could not be parsed, such as a package spec.
+    InvalidArgument,
+    /// A note id does not belong to any open note.
+    UnknownSource,
+    /// A package archive is corrupt, too large, or has unsafe paths.
+    BadArchive,
+    /// A package's `typst.toml` is missing fields or points at missing files.
+    InvalidManifest,
+    /// A package's `typst.toml` names another package or version.
+    ManifestMismatch,
+    /// A package requires a newer Typst compiler.
+    IncompatibleCompiler,
+    /// A package to export is not installed.
+    PackageNotInstalled,
+    /// A package in a bundle does not match the hash it was exported with.
+    HashMismatch,
+}
 
 #[derive(Tsify, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
@@ -784,3 +1564,256 @@ pub struct Autocomplete {
     pub offset: usize,
     pub completions: Box<[TypstCompletion]>,
 }
+
+#[test]
+fn test_apply_edits_unknown_source() {
+    let mut state = TypstState::new();
+    let id = state.create_file_id("missing.typ");
+
+    let err = state.apply_edits(&id, Vec::new()).unwrap_err();
+    assert_eq!(err.kind, TypstErrorKind::UnknownSource);
+}
+
+#[cfg(test)]
+fn synced_state(text: &str) -> (TypstState, TypstFileId) {
+    let mut state = TypstState::new();
+    let id = state.create_source_id("note", String::from("space"));
+
+    let SynthResult { synth, .. } = sync_source_state(&id, text, "", RenderTarget::Svg, &mut state);
+    state.insert_source(&id, synth.clone());
+    state.get_source_context_mut(&id).unstable_synth = synth;
+
+    (state, id)
+}
+
+#[test]
+fn test_definition() {
+    let text = "#let value = 1\n\nUse #value and #text[x]\n";
+    let (mut state, id) = synced_state(text);
+
+    let use_offset = text.find("#value").unwrap() + 2;
+    assert_eq!(
+        state.definition(&id, use_offset),
+        Some(TypstLocation::Raw { range: 5..10 })
+    );
+
+    let text_offset = text.find("#text").unwrap() + 2;
+    assert_eq!(
+        state.definition(&id, text_offset),
+        Some(TypstLocation::Std {
+            name: String::from("text")
+        })
+    );
+}
+
+#[test]
+fn test_references() {
+    let text = "#let value = 1\n#value\n#let value = 2\n#value #value\n";
+    let (mut state, id) = synced_state(text);
+
+    // The second binding shadows the first, so its uses are told apart.
+    let ranges = |locations: Vec<TypstLocation>| {
+        locations
+            .into_iter()
+            .map(|location| {
+                match location {
+                    TypstLocation::Raw { range } => range,
+                    location => panic!("unexpected location {location:?}"),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(ranges(state.references(&id, 17)), [5..10, 16..21]);
+    assert_eq!(
+        ranges(state.references(&id, text.len() - 3)),
+        [27..32, 38..43, 45..50]
+    );
+}
+
+#[test]
+fn test_signature_help_builtin() {
+    let text = "#figure([A], caption: [B])\n";
+    let (mut state, id) = synced_state(text);
+
+    let help = state.signature_help(&id, text.find("[A").unwrap() + 1).unwrap();
+    assert!(help.label.starts_with("figure(body: content, "));
+    assert!(help.docs.is_some());
+
+    let body = &help.params[help.active_param.unwrap()];
+    assert_eq!(body.label, "body: content");
+    assert!(body.positional && body.required && !body.docs.is_empty());
+
+    let help = state.signature_help(&id, text.find("[B").unwrap() + 1).unwrap();
+    let caption = &help.params[help.active_param.unwrap()];
+    assert!(caption.label.starts_with("caption: "));
+    assert!(caption.named && !caption.positional);
+
+    assert!(state.signature_help(&id, 1).is_none());
+}
+
+#[test]
+fn test_ide_queries_use_note_space_fonts() {
+    use typst::World;
+
+    let mut state = TypstState::new();
+    state.install_space_font(
+        "a",
+        include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_space_font(
+        "b",
+        include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+
+    let text = "#let value = 1\n";
+    let a = state.create_source_id("a", String::from("a"));
+    let b = state.create_source_id("b", String::from("b"));
+    for id in [&a, &b] {
+        let SynthResult { synth, .. } =
+            sync_source_state(id, text, "", RenderTarget::Svg, &mut state);
+        state.insert_source(id, synth);
+    }
+
+    // Note b was synced last, so the world still points at its space.
+    state.hover(&a, 6, 1);
+
+    let book = state.world.book();
+    assert!(book.contains_family("maple mono"));
+    assert!(!book.contains_family("new computer modern math"));
+}
+
+#[test]
+fn test_new_note_keeps_space_style() {
+    let mut state = TypstState::new();
+    let first = state.create_source_id("first", String::from("space"));
+    state.set_style(
+        &first,
+        SpaceStyle {
+            heading_numbering: Some(String::from("I.1")),
+            leading: 0.5,
+            ..SpaceStyle::default()
+        },
+    );
+
+    let second = state.create_source_id("second", String::from("space"));
+
+    for id in [&first, &second] {
+        let prelude = state.prelude(id, RenderTarget::Svg);
+        assert!(prelude.contains("#set heading(numbering:\"I.1\")\n"));
+        assert!(prelude.contains("#set par(leading:0.5em)\n"));
+    }
+}
+
+#[test]
+fn test_new_note_keeps_space_library() {
+    let mut state = TypstState::new();
+    let first = state.create_source_id("first", String::from("space"));
+    state.set_space_library(&first, Some(String::from("spaces/a \"b\"/lib.typ")));
+
+    let second = state.create_source_id("second", String::from("space"));
+
+    assert!(
+        state
+            .prelude(&second, RenderTarget::Svg)
+            .contains("#import \"/spaces/a \\\"b\\\"/lib.typ\":*\n")
+    );
+}
+
+#[test]
+fn test_registered_font_is_requested_until_installed() {
+    use typst::{
+        layout::{Frame, FrameItem},
+        text::Font,
+    };
+
+    fn uses_family(frame: &Frame, family: &str) -> bool {
+        frame.items().any(|(_, item)| {
+            match item {
+                FrameItem::Group(group) => uses_family(&group.frame, family),
+                FrameItem::Text(text) => text.font.info().family == family,
+                _ => false,
+            }
+        })
+    }
+
+    let math = include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf");
+    let math_info = Font::iter(Bytes::new(math)).next().unwrap().info().clone();
+
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.world.register_fonts(None, vec![math_info.clone()]);
+
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    let text = "$x + y$\n";
+    let result = state.compile_paged(&id, text, "");
+    assert!(result.requests.iter().any(|request| {
+        matches!(
+            request,
+            TypstRequest::Font { family, variant }
+                if *family == math_info.family && variant.weight == 400
+        )
+    }));
+
+    state.install_font(math.to_vec());
+    let result = state.compile_paged(&id, text, "");
+    assert!(result.requests.is_empty());
+
+    let document = state.get_source_context(&id).paged_document.as_ref().unwrap();
+    assert!(
+        document
+            .pages()
+            .iter()
+            .any(|page| uses_family(&page.frame, &math_info.family))
+    );
+}
+
+#[test]
+fn test_space_library_diagnostics_and_definitions() {
+    let mut state = TypstState::new();
+    state.install_font(
+        include_bytes!("../../../public/fonts/maple/ttf/MapleMono-Regular.ttf").to_vec(),
+    );
+    state.install_font(
+        include_bytes!("../../../public/fonts/new-cm/otf/NewCMMath-Regular.otf").to_vec(),
+    );
+
+    let id = state.create_source_id("note", String::from("space"));
+    state.insert_source(&id, String::new());
+
+    let path = "spaces/space/lib.typ";
+    let library_id = state.create_file_id(path);
+    state.set_space_library(&id, Some(String::from(path)));
+
+    // An error in the library points into the library, not the note.
+    let library = "/// Greets someone.\n#let greet(name) = [Hi #name]\n#let broken = 1 + \"a\"\n";
+    state.insert_source(&library_id, String::from(library));
+
+    let text = "#greet[you]\n";
+    let result = state.compile_paged(&id, text, "");
+    let diagnostic = result
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.path.is_some())
+        .unwrap();
+    let start = library.find("1 + ").unwrap();
+    assert_eq!(diagnostic.path.as_deref(), Some("/spaces/space/lib.typ"));
+    assert_eq!(diagnostic.range, start..library.len() - 1);
+
+    // Once fixed, the note sees the library's definitions.
+    let library = library.replace("1 + \"a\"", "1");
+    state.insert_source(&library_id, library);
+
+    let result = state.compile_paged(&id, text, "");
+    assert!(result.diagnostics.is_empty());
+
+    let hover = state.hover(&id, 3, 1).unwrap();
+    assert!(hover.contains("Greets someone."));
+
+    let completions = state.autocomplete(&id, 4, true).unwrap().completions;
+    assert!(completions.iter().any(|completion| completion.label == "greet"));
+}
@@ -0,0 +1,157 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::utils::typst_string;
+
+/// Typography of a space's notes, written into their prelude as set and show
+/// rules.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpaceStyle {
+    /// Text size of headings in points, from level 1 to 6.
+    pub heading_sizes: [f64; 6],
+    /// Font weight of headings, from level 1 to 6.
+    pub heading_weights: [u16; 6],
+    /// Heading numbering pattern, like `"1.1"`, or `None` for unnumbered
+    /// headings.
+    pub heading_numbering: Option<String>,
+    /// Spacing between the lines of a paragraph in em, in the editor's paged
+    /// view.
+    pub leading: f64,
+    /// Spacing between paragraphs in em, or `None` for Typst's default.
+    pub paragraph_spacing: Option<f64>,
+    /// Whether paragraphs are justified.
+    pub justify: bool,
+    /// Text size of block equations in points. Their line spacing is half of
+    /// it.
+    pub math_block_size: f64,
+    pub link_style: LinkStyle,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkStyle {
+    /// Primary-colored and underlined.
+    #[default]
+    Underline,
+    /// Primary-colored.
+    Colored,
+    /// Like the surrounding text.
+    Plain,
+}
+
+impl Default for SpaceStyle {
+    fn default() -> Self {
+        Self {
+            heading_sizes: [32.0, 28.0, 24.0, 22.0, 16.0, 14.0],
+            heading_weights: [400, 400, 400, 400, 500, 500],
+            heading_numbering: None,
+            leading: 0.125,
+            paragraph_spacing: None,
+            justify: false,
+            math_block_size: 18.0,
+            link_style: LinkStyle::Underline,
+        }
+    }
+}
+
+/// Hashes lengths by their bits, so the style can be part of the tracked
+/// prelude inputs.
+impl Hash for SpaceStyle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for size in self.heading_sizes {
+            size.to_bits().hash(state);
+        }
+        self.heading_weights.hash(state);
+        self.heading_numbering.hash(state);
+        self.leading.to_bits().hash(state);
+        self.paragraph_spacing.map(f64::to_bits).hash(state);
+        self.justify.hash(state);
+        self.math_block_size.to_bits().hash(state);
+        self.link_style.hash(state);
+    }
+}
+
+impl fmt::Display for SpaceStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const FILLS: [&str; 3] = ["primary", "secondary", "tertiary"];
+
+        for (idx, (size, weight)) in self
+            .heading_sizes
+            .iter()
+            .zip(self.heading_weights)
+            .enumerate()
+        {
+            writeln!(
+                f,
+                "#show heading.where(level:{level}):set text(fill:theme.{fill},size:{size}pt,weight:{weight})",
+                level = idx + 1,
+                fill = FILLS[idx % FILLS.len()],
+            )?;
+        }
+
+        if let Some(numbering) = &self.heading_numbering {
+            writeln!(f, "#set heading(numbering:{})", typst_string(numbering))?;
+        }
+
+        match self.link_style {
+            LinkStyle::Underline => {
+                f.write_str("#show link:set text(fill:theme.primary)\n#show link:underline\n")?;
+            }
+            LinkStyle::Colored => f.write_str("#show link:set text(fill:theme.primary)\n")?,
+            LinkStyle::Plain => {}
+        }
+
+        if self.justify {
+            f.write_str("#set par(justify:true)\n")?;
+        }
+
+        if let Some(spacing) = self.paragraph_spacing {
+            writeln!(f, "#set par(spacing:{spacing}em)")?;
+        }
+
+        let math_block = "#show math.equation.where(block:true)";
+        writeln!(f, "{math_block}:set text(size:{}pt)", self.math_block_size)?;
+        writeln!(f, "{math_block}:set par(leading:{}pt)", self.math_block_size / 2.0)
+    }
+}
+
+#[test]
+fn test_default_style_rules() {
+    let rules = SpaceStyle::default().to_string();
+
+    assert!(rules.contains(
+        "#show heading.where(level:1):set text(fill:theme.primary,size:32pt,weight:400)\n"
+    ));
+    assert!(rules.contains(
+        "#show heading.where(level:6):set text(fill:theme.tertiary,size:14pt,weight:500)\n"
+    ));
+    assert!(rules.contains("#show link:underline\n"));
+    assert!(rules.contains("#show math.equation.where(block:true):set par(leading:9pt)\n"));
+    assert!(!rules.contains("numbering"));
+    assert!(!rules.contains("justify"));
+}
+
+#[test]
+fn test_style_rules_options() {
+    let style = SpaceStyle {
+        heading_numbering: Some(String::from("1.1")),
+        paragraph_spacing: Some(1.5),
+        justify: true,
+        link_style: LinkStyle::Plain,
+        ..SpaceStyle::default()
+    };
+    let rules = style.to_string();
+
+    assert!(rules.contains("#set heading(numbering:\"1.1\")\n"));
+    assert!(rules.contains("#set par(justify:true)\n"));
+    assert!(rules.contains("#set par(spacing:1.5em)\n"));
+    assert!(!rules.contains("link"));
+}
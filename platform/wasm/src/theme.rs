@@ -1,8 +1,15 @@
-use std::fmt;
+use std::{f64::consts::PI, fmt, sync::LazyLock};
 
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+/// Seed of the default theme, Material's baseline purple.
+const BASELINE_SEED: Rgb = Rgb(0x67, 0x50, 0xa4);
+/// HCT hue of Material's error palette, a red.
+const ERROR_HUE: f64 = 25.0;
+
+/// The color roles of a Material 3 scheme, available to Typst as the `theme`
+/// dictionary.
 #[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
 #[wasm_bindgen]
@@ -32,42 +39,36 @@ pub struct ThemeColors {
     on_error: Rgb,
     error_container: Rgb,
     on_error_container: Rgb,
+
+    surface: Rgb,
+    on_surface: Rgb,
+    surface_variant: Rgb,
+    on_surface_variant: Rgb,
+    surface_dim: Rgb,
+    surface_bright: Rgb,
+    surface_container_lowest: Rgb,
+    surface_container_low: Rgb,
+    surface_container: Rgb,
+    surface_container_high: Rgb,
+    surface_container_highest: Rgb,
+
+    inverse_surface: Rgb,
+    inverse_on_surface: Rgb,
+    inverse_primary: Rgb,
 }
 
 impl Default for ThemeColors {
+    /// The light scheme of Material's baseline purple.
     fn default() -> Self {
-        Self {
-            background: Rgb::WHITE,
-            on_background: Rgb::BLACK,
-
-            outline: Rgb::BLACK,
-            outline_variant: Rgb::BLACK,
-
-            primary: Rgb::BLACK,
-            on_primary: Rgb::WHITE,
-            primary_container: Rgb::BLACK,
-            on_primary_container: Rgb::WHITE,
-
-            secondary: Rgb::BLACK,
-            on_secondary: Rgb::WHITE,
-            secondary_container: Rgb::BLACK,
-            on_secondary_container: Rgb::WHITE,
-
-            tertiary: Rgb::BLACK,
-            on_tertiary: Rgb::WHITE,
-            tertiary_container: Rgb::BLACK,
-            on_tertiary_container: Rgb::WHITE,
-
-            error: Rgb::BLACK,
-            on_error: Rgb::WHITE,
-            error_container: Rgb::BLACK,
-            on_error_container: Rgb::WHITE,
-        }
+        Self::from_seed(BASELINE_SEED, false)
     }
 }
 
 #[wasm_bindgen]
 impl ThemeColors {
+    /// A theme with the given roles. The surface and inverse roles are
+    /// derived from the background, its foreground and the primary colors;
+    /// use [`ThemeColors::from_seed`] for a complete scheme.
     #[must_use]
     #[allow(clippy::missing_const_for_fn, clippy::too_many_arguments)]
     #[wasm_bindgen(constructor)]
@@ -124,47 +125,461 @@ impl ThemeColors {
             on_error,
             error_container,
             on_error_container,
+
+            surface: background,
+            on_surface: on_background,
+            surface_variant: background,
+            on_surface_variant: on_background,
+            surface_dim: background,
+            surface_bright: background,
+            surface_container_lowest: background,
+            surface_container_low: background,
+            surface_container: background,
+            surface_container_high: background,
+            surface_container_highest: background,
+
+            inverse_surface: on_background,
+            inverse_on_surface: background,
+            inverse_primary: primary_container,
+        }
+    }
+
+    /// Generates the light or dark Material 3 scheme of a seed color, with
+    /// the tonal spot variant Material uses by default.
+    ///
+    /// Palettes are built in Material's HCT color space, CAM16 hue and chroma
+    /// with CIE L* as tone, so roles match those of Material's own libraries.
+    #[must_use]
+    #[wasm_bindgen(js_name = "fromSeed")]
+    pub fn from_seed(seed: Rgb, dark: bool) -> Self {
+        let hue = Cam16::from_rgb(seed).hue;
+
+        let primary = TonalPalette::new(hue, 36.0);
+        let secondary = TonalPalette::new(hue, 16.0);
+        let tertiary = TonalPalette::new(hue + 60.0, 24.0);
+        let error = TonalPalette::new(ERROR_HUE, 84.0);
+        let neutral = TonalPalette::new(hue, 6.0);
+        let neutral_variant = TonalPalette::new(hue, 8.0);
+
+        // Tone of a role in the light and the dark scheme.
+        let tone = |palette: &TonalPalette, light: f64, dark_tone: f64| {
+            palette.tone(if dark { dark_tone } else { light })
+        };
+
+        Self {
+            background: tone(&neutral, 99.0, 10.0),
+            on_background: tone(&neutral, 10.0, 90.0),
+
+            outline: tone(&neutral_variant, 50.0, 60.0),
+            outline_variant: tone(&neutral_variant, 80.0, 30.0),
+
+            primary: tone(&primary, 40.0, 80.0),
+            on_primary: tone(&primary, 100.0, 20.0),
+            primary_container: tone(&primary, 90.0, 30.0),
+            on_primary_container: tone(&primary, 10.0, 90.0),
+
+            secondary: tone(&secondary, 40.0, 80.0),
+            on_secondary: tone(&secondary, 100.0, 20.0),
+            secondary_container: tone(&secondary, 90.0, 30.0),
+            on_secondary_container: tone(&secondary, 10.0, 90.0),
+
+            tertiary: tone(&tertiary, 40.0, 80.0),
+            on_tertiary: tone(&tertiary, 100.0, 20.0),
+            tertiary_container: tone(&tertiary, 90.0, 30.0),
+            on_tertiary_container: tone(&tertiary, 10.0, 90.0),
+
+            error: tone(&error, 40.0, 80.0),
+            on_error: tone(&error, 100.0, 20.0),
+            error_container: tone(&error, 90.0, 30.0),
+            on_error_container: tone(&error, 10.0, 90.0),
+
+            surface: tone(&neutral, 98.0, 6.0),
+            on_surface: tone(&neutral, 10.0, 90.0),
+            surface_variant: tone(&neutral_variant, 90.0, 30.0),
+            on_surface_variant: tone(&neutral_variant, 30.0, 80.0),
+            surface_dim: tone(&neutral, 87.0, 6.0),
+            surface_bright: tone(&neutral, 98.0, 24.0),
+            surface_container_lowest: tone(&neutral, 100.0, 4.0),
+            surface_container_low: tone(&neutral, 96.0, 10.0),
+            surface_container: tone(&neutral, 94.0, 12.0),
+            surface_container_high: tone(&neutral, 92.0, 17.0),
+            surface_container_highest: tone(&neutral, 90.0, 22.0),
+
+            inverse_surface: tone(&neutral, 20.0, 90.0),
+            inverse_on_surface: tone(&neutral, 95.0, 20.0),
+            inverse_primary: tone(&primary, 80.0, 40.0),
         }
     }
 }
 
+impl ThemeColors {
+    /// Every role with its name in the `theme` dictionary.
+    const fn roles(&self) -> [(&'static str, Rgb); 34] {
+        [
+            ("background", self.background),
+            ("on-background", self.on_background),
+            ("outline", self.outline),
+            ("outline-variant", self.outline_variant),
+            ("primary", self.primary),
+            ("on-primary", self.on_primary),
+            ("primary-container", self.primary_container),
+            ("on-primary-container", self.on_primary_container),
+            ("secondary", self.secondary),
+            ("on-secondary", self.on_secondary),
+            ("secondary-container", self.secondary_container),
+            ("on-secondary-container", self.on_secondary_container),
+            ("tertiary", self.tertiary),
+            ("on-tertiary", self.on_tertiary),
+            ("tertiary-container", self.tertiary_container),
+            ("on-tertiary-container", self.on_tertiary_container),
+            ("error", self.error),
+            ("on-error", self.on_error),
+            ("error-container", self.error_container),
+            ("on-error-container", self.on_error_container),
+            ("surface", self.surface),
+            ("on-surface", self.on_surface),
+            ("surface-variant", self.surface_variant),
+            ("on-surface-variant", self.on_surface_variant),
+            ("surface-dim", self.surface_dim),
+            ("surface-bright", self.surface_bright),
+            ("surface-container-lowest", self.surface_container_lowest),
+            ("surface-container-low", self.surface_container_low),
+            ("surface-container", self.surface_container),
+            ("surface-container-high", self.surface_container_high),
+            ("surface-container-highest", self.surface_container_highest),
+            ("inverse-surface", self.inverse_surface),
+            ("inverse-on-surface", self.inverse_on_surface),
+            ("inverse-primary", self.inverse_primary),
+        ]
+    }
+}
+
 impl fmt::Display for ThemeColors {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "(background:{},on-background:{},outline:{},outline-variant:{},primary:{},on-primary:{},primary-container:{},on-primary-container:{},secondary:{},on-secondary:{},secondary-container:{},on-secondary-container:{},tertiary:{},on-tertiary:{},tertiary-container:{},on-tertiary-container:{},error:{},on-error:{},error-container:{},on-error-container:{})",
-            self.background,
-            self.on_background,
-            self.outline,
-            self.outline_variant,
-            self.primary,
-            self.on_primary,
-            self.primary_container,
-            self.on_primary_container,
-            self.secondary,
-            self.on_secondary,
-            self.secondary_container,
-            self.on_secondary_container,
-            self.tertiary,
-            self.on_tertiary,
-            self.tertiary_container,
-            self.on_tertiary_container,
-            self.error,
-            self.on_error,
-            self.error_container,
-            self.on_error_container,
-        )
+        f.write_str("(")?;
+
+        for (idx, (name, color)) in self.roles().into_iter().enumerate() {
+            if idx > 0 {
+                f.write_str(",")?;
+            }
+
+            write!(f, "{name}:{color}")?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+/// The colors of one HCT hue and chroma at every tone.
+struct TonalPalette {
+    hue: f64,
+    chroma: f64,
+}
+
+impl TonalPalette {
+    fn new(hue: f64, chroma: f64) -> Self {
+        Self {
+            hue: hue.rem_euclid(360.0),
+            chroma,
+        }
+    }
+
+    /// The color at `tone`, from 0 (black) to 100 (white), with as much of the
+    /// palette's chroma as fits in sRGB.
+    ///
+    /// Like Material's HCT solver, keeps the hue and the tone exactly, and
+    /// lowers the chroma to the edge of the gamut when it does not fit.
+    fn tone(&self, tone: f64) -> Rgb {
+        if self.chroma < 1e-4 || !(1e-4..=100.0 - 1e-4).contains(&tone) {
+            return Rgb::from_lstar(tone);
+        }
+
+        let y = y_from_lstar(tone) / 100.0;
+
+        if let Some(rgb) = solve_lightness(self.hue, self.chroma, y) {
+            return Rgb::from_linear(rgb);
+        }
+
+        let mut low = 0.0;
+        let mut high = self.chroma;
+        let mut edge = None;
+
+        for _ in 0..24 {
+            let mid = f64::midpoint(low, high);
+
+            match solve_lightness(self.hue, mid, y) {
+                Some(rgb) => {
+                    edge = Some(rgb);
+                    low = mid;
+                }
+                None => high = mid,
+            }
+        }
+
+        edge.map_or_else(|| Rgb::from_lstar(tone), Rgb::from_linear)
+    }
+}
+
+/// Finds the CAM16 lightness J at which `hue` and `chroma` have CIE luminance
+/// `y`, with Newton's method, and returns that color in linear sRGB, or `None`
+/// when it is out of gamut.
+fn solve_lightness(hue: f64, chroma: f64, y: f64) -> Option<[f64; 3]> {
+    let mut j = (y * 100.0).sqrt() * 11.0;
+
+    for round in 0..5 {
+        let rgb = Cam16 { j, chroma, hue }.to_linear();
+
+        if rgb.iter().any(|channel| *channel < 0.0) {
+            return None;
+        }
+
+        let luminance = transform(&LINEAR_TO_XYZ, rgb)[1];
+        if luminance <= 0.0 {
+            return None;
+        }
+
+        if round == 4 || (luminance - y).abs() < 2e-5 {
+            return rgb
+                .iter()
+                .all(|channel| *channel <= 1.0001)
+                .then_some(rgb);
+        }
+
+        // Luminance grows about with the square of J.
+        j -= (luminance - y) * j / (2.0 * luminance);
+    }
+
+    None
+}
+
+/// CIE XYZ of D65 white, with Y at 100.
+const WHITE_POINT: [f64; 3] = [95.047, 100.0, 108.883];
+const EPSILON: f64 = 216.0 / 24389.0;
+const KAPPA: f64 = 24389.0 / 27.0;
+
+const LINEAR_TO_XYZ: [[f64; 3]; 3] = [
+    [0.412_338_95, 0.357_620_64, 0.180_510_42],
+    [0.2126, 0.7152, 0.0722],
+    [0.019_321_41, 0.119_163_82, 0.950_344_78],
+];
+const XYZ_TO_LINEAR: [[f64; 3]; 3] = [
+    [3.241_377_479_238_868_5, -1.537_665_240_285_185, -0.498_853_668_462_680_5],
+    [-0.969_145_251_300_532_1, 1.875_885_345_106_787, 0.041_565_856_169_120_61],
+    [0.055_620_936_896_913_05, -0.203_955_245_647_421_23, 1.057_179_911_122_033_5],
+];
+const XYZ_TO_CAM16: [[f64; 3]; 3] = [
+    [0.401_288, 0.650_173, -0.051_461],
+    [-0.250_268, 1.204_414, 0.045_854],
+    [-0.002_079, 0.048_952, 0.953_127],
+];
+const CAM16_TO_XYZ: [[f64; 3]; 3] = [
+    [1.862_067_86, -1.011_254_63, 0.149_186_77],
+    [0.387_526_54, 0.621_447_44, -0.008_973_98],
+    [-0.015_841_5, -0.034_122_94, 1.049_964_44],
+];
+
+fn dot(row: [f64; 3], vector: [f64; 3]) -> f64 {
+    row[0].mul_add(vector[0], row[1].mul_add(vector[1], row[2] * vector[2]))
+}
+
+fn transform(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| dot(row, vector))
+}
+
+/// CIE Y, from 0 to 100, of a CIE L*.
+fn y_from_lstar(lstar: f64) -> f64 {
+    let f = (lstar + 16.0) / 116.0;
+    let cube = f * f * f;
+
+    if cube > EPSILON {
+        100.0 * cube
+    } else {
+        100.0 * lstar / KAPPA
+    }
+}
+
+/// CAM16 viewing conditions of HCT: D65 white, seen on a mid-grey background
+/// of L* 50 under an average surround.
+struct ViewingConditions {
+    n: f64,
+    aw: f64,
+    nbb: f64,
+    c: f64,
+    nc: f64,
+    z: f64,
+    fl: f64,
+    rgb_d: [f64; 3],
+}
+
+static VIEWING_CONDITIONS: LazyLock<ViewingConditions> = LazyLock::new(|| {
+    let adapting_luminance = 200.0 / PI * y_from_lstar(50.0) / 100.0;
+    let rgb_w = transform(&XYZ_TO_CAM16, WHITE_POINT);
+
+    // An average surround.
+    let f = 1.0;
+    let c = 0.69;
+
+    let d = (f * (-1.0 / 3.6f64).mul_add(((-adapting_luminance - 42.0) / 92.0).exp(), 1.0))
+        .clamp(0.0, 1.0);
+    let rgb_d = rgb_w.map(|white| d.mul_add(100.0 / white, 1.0 - d));
+
+    let k = 1.0 / 5.0f64.mul_add(adapting_luminance, 1.0);
+    let k4 = k.powi(4);
+    let k4f = 1.0 - k4;
+    let fl = (0.1 * k4f * k4f).mul_add((5.0 * adapting_luminance).cbrt(), k4 * adapting_luminance);
+
+    let n = y_from_lstar(50.0) / WHITE_POINT[1];
+    let nbb = 0.725 / n.powf(0.2);
+    let [r, g, b] = [0, 1, 2].map(|idx| adapt(fl * rgb_d[idx] * rgb_w[idx] / 100.0));
+
+    ViewingConditions {
+        n,
+        aw: 0.05f64.mul_add(b, 2.0f64.mul_add(r, g)) * nbb,
+        nbb,
+        c,
+        nc: f,
+        z: 1.48 + n.sqrt(),
+        fl,
+        rgb_d,
+    }
+});
+
+/// The CAM16 post-adaptation response of an adapted cone response.
+fn adapt(component: f64) -> f64 {
+    let factor = component.abs().powf(0.42);
+
+    component.signum() * 400.0 * factor / (factor + 27.13)
+}
+
+/// A color in CAM16 under [`VIEWING_CONDITIONS`].
+struct Cam16 {
+    /// Lightness J.
+    j: f64,
+    chroma: f64,
+    /// Hue in degrees.
+    hue: f64,
+}
+
+impl Cam16 {
+    fn from_rgb(rgb: Rgb) -> Self {
+        let vc = &*VIEWING_CONDITIONS;
+
+        let xyz = transform(&LINEAR_TO_XYZ, rgb.to_linear()).map(|value| value * 100.0);
+        let cone = transform(&XYZ_TO_CAM16, xyz);
+        let adapted = [0, 1, 2].map(|idx| adapt(vc.fl * vc.rgb_d[idx] * cone[idx] / 100.0));
+
+        // Opponent color dimensions.
+        let red_green = dot([11.0, -12.0, 1.0], adapted) / 11.0;
+        let yellow_blue = dot([1.0, 1.0, -2.0], adapted) / 9.0;
+        let u = dot([20.0, 20.0, 21.0], adapted) / 20.0;
+        let p2 = dot([40.0, 20.0, 1.0], adapted) / 20.0;
+
+        let hue = yellow_blue.atan2(red_green).to_degrees().rem_euclid(360.0);
+        let j = 100.0 * (p2 * vc.nbb / vc.aw).powf(vc.c * vc.z);
+
+        let e_hue = 0.25 * ((hue.to_radians() + 2.0).cos() + 3.8);
+        let p1 = 50000.0 / 13.0 * e_hue * vc.nc * vc.nbb;
+        let t = p1 * red_green.hypot(yellow_blue) / (u + 0.305);
+        let alpha = t.powf(0.9) * (1.64 - 0.29f64.powf(vc.n)).powf(0.73);
+
+        Self {
+            j,
+            chroma: alpha * (j / 100.0).sqrt(),
+            hue,
+        }
+    }
+
+    /// The color in linear sRGB, which may be out of gamut.
+    fn to_linear(&self) -> [f64; 3] {
+        let vc = &*VIEWING_CONDITIONS;
+
+        let alpha = if self.chroma == 0.0 || self.j == 0.0 {
+            0.0
+        } else {
+            self.chroma / (self.j / 100.0).sqrt()
+        };
+        let t = (alpha / (1.64 - 0.29f64.powf(vc.n)).powf(0.73)).powf(1.0 / 0.9);
+        let (sin, cos) = self.hue.to_radians().sin_cos();
+
+        let e_hue = 0.25 * ((self.hue.to_radians() + 2.0).cos() + 3.8);
+        let ac = vc.aw * (self.j / 100.0).powf(1.0 / vc.c / vc.z);
+        let p1 = e_hue * (50000.0 / 13.0) * vc.nc * vc.nbb;
+        let p2 = ac / vc.nbb;
+
+        let gamma = 23.0 * (p2 + 0.305) * t / dot([23.0, 11.0, 108.0], [p1, t * cos, t * sin]);
+        let opponent = [p2, gamma * cos, gamma * sin];
+
+        let adapted = [
+            [460.0, 451.0, 288.0],
+            [460.0, -891.0, -261.0],
+            [460.0, -220.0, -6300.0],
+        ]
+        .map(|row| dot(row, opponent) / 1403.0);
+        let cone = [0, 1, 2].map(|idx| {
+            let response = adapted[idx];
+            let base = (27.13 * response.abs() / (400.0 - response.abs())).max(0.0);
+
+            response.signum() * (100.0 / vc.fl) * base.powf(1.0 / 0.42) / vc.rgb_d[idx]
+        });
+
+        let xyz = transform(&CAM16_TO_XYZ, cone);
+
+        transform(&XYZ_TO_LINEAR, xyz).map(|value| value / 100.0)
     }
 }
 
 #[allow(clippy::unsafe_derive_deserialize)]
-#[derive(Default, Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct Rgb(u8, u8, u8);
 
 impl Rgb {
     pub const BLACK: Self = Self(0, 0, 0);
     pub const WHITE: Self = Self(255, 255, 255);
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from_linear(rgb: [f64; 3]) -> Self {
+        let encode = |channel: f64| {
+            let channel = channel.clamp(0.0, 1.0);
+            let encoded = if channel <= 0.003_130_8 {
+                channel * 12.92
+            } else {
+                1.055f64.mul_add(channel.powf(1.0 / 2.4), -0.055)
+            };
+
+            (encoded * 255.0).round() as u8
+        };
+
+        Self(encode(rgb[0]), encode(rgb[1]), encode(rgb[2]))
+    }
+
+    /// The grey of a CIE L*.
+    fn from_lstar(lstar: f64) -> Self {
+        Self::from_linear([y_from_lstar(lstar) / 100.0; 3])
+    }
+
+    fn to_linear(self) -> [f64; 3] {
+        [self.0, self.1, self.2].map(|channel| {
+            let channel = f64::from(channel) / 255.0;
+            if channel <= 0.040_45 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    }
+
+    /// CIE L*, the tone of the color in HCT.
+    #[cfg(test)]
+    fn lstar(self) -> f64 {
+        let y = transform(&LINEAR_TO_XYZ, self.to_linear())[1];
+
+        if y > EPSILON {
+            116.0f64.mul_add(y.cbrt(), -16.0)
+        } else {
+            KAPPA * y
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -188,3 +603,97 @@ impl fmt::Display for Rgb {
         write!(f, "rgb({},{},{})", self.0, self.1, self.2)
     }
 }
+
+#[test]
+fn test_tones_match_lightness() {
+    let palette = TonalPalette::new(Cam16::from_rgb(BASELINE_SEED).hue, 36.0);
+
+    assert_eq!(palette.tone(0.0), Rgb::BLACK);
+    assert_eq!(palette.tone(100.0), Rgb::WHITE);
+
+    for tone in [10.0, 40.0, 50.0, 90.0] {
+        let lightness = palette.tone(tone).lstar();
+        assert!((lightness - tone).abs() < 0.5, "{tone}: {lightness}");
+    }
+}
+
+#[test]
+fn test_palette_matches_material() {
+    // Material's tonal palette of pure blue.
+    let blue = Cam16::from_rgb(Rgb(0, 0, 0xff));
+    let palette = TonalPalette::new(blue.hue, blue.chroma);
+
+    for (tone, rgb) in [
+        (100.0, Rgb(0xff, 0xff, 0xff)),
+        (95.0, Rgb(0xf1, 0xef, 0xff)),
+        (90.0, Rgb(0xe0, 0xe0, 0xff)),
+        (80.0, Rgb(0xbe, 0xc2, 0xff)),
+        (60.0, Rgb(0x7c, 0x84, 0xff)),
+        (40.0, Rgb(0x34, 0x3d, 0xff)),
+        (30.0, Rgb(0x00, 0x00, 0xef)),
+        (10.0, Rgb(0x00, 0x00, 0x6e)),
+        (0.0, Rgb(0x00, 0x00, 0x00)),
+    ] {
+        assert_eq!(palette.tone(tone), rgb, "tone {tone}");
+    }
+}
+
+#[test]
+fn test_baseline_tones() {
+    // Material's baseline primary and on-primary, from a palette with the
+    // seed's hue and a chroma of at least 48.
+    let seed = Cam16::from_rgb(BASELINE_SEED);
+    let primary = TonalPalette::new(seed.hue, seed.chroma.max(48.0));
+
+    assert_eq!(primary.tone(40.0), BASELINE_SEED);
+    assert_eq!(primary.tone(100.0), Rgb(0xff, 0xff, 0xff));
+}
+
+#[test]
+fn test_achromatic_tones_are_grey() {
+    let palette = TonalPalette::new(0.0, 0.0);
+
+    assert_eq!(palette.tone(50.0), Rgb(0x77, 0x77, 0x77));
+    assert_eq!(palette.tone(90.0), Rgb(0xe2, 0xe2, 0xe2));
+}
+
+#[test]
+fn test_seed_keeps_hue() {
+    let seed = Rgb(0x1e, 0x88, 0xe5);
+    let theme = ThemeColors::from_seed(seed, false);
+
+    let seed_hue = Cam16::from_rgb(seed).hue;
+    let primary_hue = Cam16::from_rgb(theme.primary).hue;
+    assert!((seed_hue - primary_hue).abs() < 2.0, "{seed_hue}: {primary_hue}");
+}
+
+#[test]
+fn test_default_error_is_red() {
+    let Rgb(r, g, b) = ThemeColors::default().error;
+    assert!(r > 2 * g && r > 2 * b, "rgb({r},{g},{b})");
+}
+
+#[test]
+fn test_dark_scheme_inverts_tones() {
+    let light = ThemeColors::from_seed(BASELINE_SEED, false);
+    let dark = ThemeColors::from_seed(BASELINE_SEED, true);
+
+    assert!(light.background.lstar() > 90.0);
+    assert!(dark.background.lstar() < 20.0);
+    assert_eq!(light.inverse_primary, dark.primary);
+    assert_eq!(light.on_primary, Rgb::WHITE);
+
+    for ((name, light), (_, dark)) in light.roles().into_iter().zip(dark.roles()) {
+        assert_ne!(light, dark, "{name}");
+    }
+}
+
+#[test]
+fn test_theme_dictionary_has_every_role() {
+    let theme = ThemeColors::default().to_string();
+
+    assert!(theme.starts_with("(background:rgb("));
+    assert!(theme.contains(",surface-container-highest:rgb("));
+    assert!(theme.contains(",inverse-primary:rgb("));
+    assert_eq!(theme.matches(':').count(), 34);
+}
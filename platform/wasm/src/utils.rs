@@ -8,3 +8,35 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+/// Quotes text as a Typst string literal, so it can be spliced into generated
+/// source without changing its meaning.
+pub fn typst_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            ch if ch.is_control() => {
+                literal.push_str(&format!("\\u{{{:x}}}", u32::from(ch)));
+            }
+            ch => literal.push(ch),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+#[test]
+fn test_typst_string() {
+    assert_eq!(typst_string("Notes — 日本"), "\"Notes — 日本\"");
+    assert_eq!(typst_string("a \"b\" \\c"), "\"a \\\"b\\\" \\\\c\"");
+    assert_eq!(typst_string("a\nb\u{7}"), "\"a\\nb\\u{7}\"");
+    assert_eq!(typst_string("\")#panic()"), "\"\\\")#panic()\"");
+}
@@ -1,18 +1,25 @@
-use dashmap::DashSet;
+use dashmap::{DashMap, DashSet};
 use rustc_hash::FxHashMap;
-use time::{OffsetDateTime, UtcOffset};
+use time::{Date, OffsetDateTime, UtcOffset};
 use typst::{
     Feature, Library, LibraryExt, World,
     diag::{FileError, FileResult},
-    foundations::{Bytes, Datetime, Duration},
+    foundations::{Bytes, Datetime, Dict, Duration},
     syntax::{FileId, Source},
-    text::{Font, FontBook},
+    text::{Font, FontBook, FontInfo, FontVariant},
     utils::LazyHash,
 };
 use typst_ide::IdeWorld;
-use typst_syntax::{VirtualPath, VirtualRoot, package::PackageSpec};
+use typst_syntax::{
+    RootedPath, VirtualPath, VirtualRoot,
+    package::{PackageManifest, PackageSpec},
+};
 
-use crate::{fonts::FontLoader, source::IndexMapper};
+use crate::{
+    fonts::{FontLoader, FontSet},
+    package::{self, LocalPackage},
+    source::IndexMapper,
+};
 
 /// Implementation of Typst's `World` for Mnemo, managing all loaded files,
 /// fonts, and compilation state.
@@ -22,12 +29,32 @@ pub struct MnemoWorld {
     pub synth_id: Option<FileId>,
     /// The raw (user/editor/origin) source file id.
     pub raw_id: Option<FileId>,
+    /// The space of the note being compiled, selecting its font set.
+    pub space_id: Option<String>,
     /// All loaded files (sources and binaries) by id.
     pub files: FxHashMap<FileId, FileSlot>,
+    /// Manifests of the installed packages, whose files are in `files`.
+    pub packages: FxHashMap<PackageSpec, PackageManifest>,
+    /// Project directories registered as `@local` packages.
+    pub local_packages: FxHashMap<PackageSpec, String>,
+    /// Copies of project sources under the ids of the local packages they are
+    /// in, reparsed incrementally when the project sources change.
+    local_sources: DashMap<FileId, Source>,
     /// Index mapping between raw and synth sources.
     pub index_mapper: IndexMapper,
+    /// Date of the note being compiled, returned by `datetime.today()` when
+    /// set.
+    pub date: Option<Date>,
+    /// UTC offset of each space, used by `datetime.today()` in its notes.
+    pub utc_offsets: FxHashMap<String, UtcOffset>,
+    /// Time used instead of the current one, if pinned.
+    now: Option<OffsetDateTime>,
     /// The Typst standard library for this world.
     library: LazyHash<Library>,
+    /// Libraries of the notes with `sys.inputs`, by synth id, and the inputs
+    /// each was built with. Every note hashes its own library, so setting
+    /// one note's inputs leaves other notes' cached compiles valid.
+    note_libraries: FxHashMap<FileId, (Dict, LazyHash<Library>)>,
     /// Font loader and font book.
     font_loader: FontLoader,
 
@@ -37,23 +64,31 @@ pub struct MnemoWorld {
     pub requested_files: DashSet<VirtualPath>,
     /// Packages requested by Typst but not yet loaded.
     pub requested_packages: DashSet<PackageSpec>,
+    /// Registered font faces selected by Typst but not yet loaded.
+    pub requested_fonts: DashSet<(String, FontVariant)>,
 }
 
 impl Default for MnemoWorld {
     fn default() -> Self {
-        let features = [Feature::Html, Feature::A11yExtras].into_iter().collect();
-        let library = Library::builder().with_features(features).build();
-
         Self {
             synth_id: None,
             raw_id: None,
+            space_id: None,
             files: FxHashMap::default(),
+            packages: FxHashMap::default(),
+            local_packages: FxHashMap::default(),
+            local_sources: DashMap::default(),
             index_mapper: IndexMapper::default(),
-            library: LazyHash::new(library),
+            date: None,
+            utc_offsets: FxHashMap::default(),
+            now: None,
+            library: LazyHash::new(build_library(Dict::new())),
+            note_libraries: FxHashMap::default(),
             font_loader: FontLoader::default(),
             requested_sources: DashSet::default(),
             requested_files: DashSet::default(),
             requested_packages: DashSet::default(),
+            requested_fonts: DashSet::default(),
         }
     }
 }
@@ -67,6 +102,22 @@ impl MnemoWorld {
 
     pub fn remove_source(&mut self, id: &FileId) {
         self.files.remove(id);
+        self.note_libraries.remove(id);
+    }
+
+    /// Sets the `sys.inputs` of the note compiled from `synth_id`. Empty
+    /// inputs go back to the shared library.
+    pub fn set_inputs(&mut self, synth_id: FileId, inputs: Dict) {
+        if inputs.is_empty() {
+            self.note_libraries.remove(&synth_id);
+        } else if self
+            .note_libraries
+            .get(&synth_id)
+            .is_none_or(|(current, _)| *current != inputs)
+        {
+            let library = LazyHash::new(build_library(inputs.clone()));
+            self.note_libraries.insert(synth_id, (inputs, library));
+        }
     }
 
     pub fn insert_file(&mut self, id: FileId, bytes: Bytes) {
@@ -85,22 +136,143 @@ impl MnemoWorld {
         self.files.get(&id)
     }
 
+    /// Pins the time `datetime.today()` is computed from, or unpins it with
+    /// `None`. Compiles with a pinned time are deterministic.
+    pub const fn pin_now(&mut self, now: Option<OffsetDateTime>) {
+        self.now = now;
+    }
+
     // pub fn set_source(&mut self, id: &FileId, text: &str) {
     //     self.files.get_mut(id).unwrap().replace(text);
     // }
 
+    /// Removes an installed package and all of its files, returning whether
+    /// it was installed.
+    pub fn uninstall_package(&mut self, spec: &PackageSpec) -> bool {
+        self.files.retain(|id, _| {
+            !matches!(id.root(), VirtualRoot::Package(root) if root == spec)
+        });
+
+        self.local_packages.remove(spec);
+        self.local_sources.retain(|id, _| {
+            !matches!(id.root(), VirtualRoot::Package(root) if root == spec)
+        });
+
+        self.packages.remove(spec).is_some()
+    }
+
+    /// Registers a directory of project files as a local package, replacing
+    /// any package with the same spec.
+    pub fn register_local_package(&mut self, package: LocalPackage) {
+        self.uninstall_package(&package.spec);
+
+        if let Some(id) = package.file_id("typst.toml") {
+            self.insert_file(id, Bytes::from_string(package.toml));
+        }
+
+        self.local_packages.insert(package.spec.clone(), package.dir);
+        self.packages.insert(package.spec, package.manifest);
+    }
+
+    /// The project file a file of a local package is read from.
+    fn local_project_id(&self, id: FileId) -> Option<FileId> {
+        let VirtualRoot::Package(spec) = id.root() else {
+            return None;
+        };
+
+        let dir = self.local_packages.get(spec)?;
+        let path = format!("{dir}{}", id.vpath().get_with_slash());
+
+        Some(FileId::new(RootedPath::new(
+            VirtualRoot::Project,
+            VirtualPath::new(&path).ok()?,
+        )))
+    }
+
+    /// Whether a project file is in the directory of a local package.
+    fn in_local_package(&self, id: FileId) -> bool {
+        let path = id.vpath().get_with_slash();
+        let path = path.trim_start_matches('/');
+
+        matches!(id.root(), VirtualRoot::Project)
+            && self.local_packages.values().any(|dir| {
+                path.strip_prefix(dir.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    fn local_source(&self, id: FileId, project: &Source) -> Source {
+        let mut source = self
+            .local_sources
+            .entry(id)
+            .or_insert_with(|| Source::new(id, project.text().to_string()));
+
+        if source.text() != project.text() {
+            source.replace(project.text());
+        }
+
+        source.clone()
+    }
+
+    /// Packages imported by the sources of installed packages that are not
+    /// installed themselves.
+    #[must_use]
+    pub fn missing_dependencies(&self) -> Vec<PackageSpec> {
+        let mut missing = Vec::new();
+
+        for (id, file) in &self.files {
+            let in_package =
+                matches!(id.root(), VirtualRoot::Package(..)) || self.in_local_package(*id);
+
+            let (true, Some(source)) = (in_package, file.source()) else {
+                continue;
+            };
+
+            for spec in package::imported_packages(source) {
+                if !self.packages.contains_key(&spec) && !missing.contains(&spec) {
+                    missing.push(spec);
+                }
+            }
+        }
+
+        missing
+    }
+
     pub fn install_font(&mut self, bytes: Vec<u8>) {
-        self.font_loader.install(bytes);
+        self.font_loader.install(None, bytes);
+    }
+
+    pub fn install_space_font(&mut self, space_id: &str, bytes: Vec<u8>) {
+        self.font_loader.install(Some(space_id), bytes);
+    }
+
+    pub fn register_fonts(&mut self, space_id: Option<&str>, infos: Vec<FontInfo>) {
+        self.font_loader.register(space_id, infos);
+    }
+
+    pub fn uninstall_space_font(&mut self, space_id: &str, family: &str) -> bool {
+        self.font_loader.uninstall_for_space(space_id, family)
+    }
+
+    #[must_use]
+    pub const fn font_loader(&self) -> &FontLoader {
+        &self.font_loader
+    }
+
+    fn font_set(&self) -> &FontSet {
+        self.font_loader.set(self.space_id.as_deref())
     }
 }
 
 impl World for MnemoWorld {
     fn library(&self) -> &LazyHash<Library> {
-        &self.library
+        self.synth_id
+            .and_then(|synth_id| self.note_libraries.get(&synth_id))
+            .map_or(&self.library, |(_, library)| library)
     }
 
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.font_loader.book
+        &self.font_set().book
     }
 
     fn main(&self) -> FileId {
@@ -110,6 +282,14 @@ impl World for MnemoWorld {
     fn source(&self, id: FileId) -> FileResult<Source> {
         if let Some(source) = self.get_source(id) {
             Ok(source.clone())
+        } else if let Some(project_id) = self.local_project_id(id) {
+            if let Some(project) = self.get_source(project_id) {
+                Ok(self.local_source(id, project))
+            } else {
+                self.requested_sources.insert(project_id.vpath().clone());
+
+                Err(FileError::Other(None))
+            }
         } else {
             match id.root() {
                 VirtualRoot::Project => self.requested_sources.insert(id.vpath().clone()),
@@ -123,6 +303,14 @@ impl World for MnemoWorld {
     fn file(&self, id: FileId) -> FileResult<Bytes> {
         if let Some(file) = self.get_file(id) {
             Ok(file.bytes())
+        } else if let Some(project_id) = self.local_project_id(id) {
+            if let Some(project) = self.get_file(project_id) {
+                Ok(project.bytes())
+            } else {
+                self.requested_files.insert(project_id.vpath().clone());
+
+                Err(FileError::Other(None))
+            }
         } else {
             match id.root() {
                 VirtualRoot::Project => self.requested_files.insert(id.vpath().clone()),
@@ -134,22 +322,51 @@ impl World for MnemoWorld {
     }
 
     fn font(&self, index: usize) -> Option<Font> {
-        Some(self.font_loader.fonts[index].clone())
+        let slot = self.font_set().slots.get(index)?;
+
+        if slot.font.is_none() {
+            self.requested_fonts
+                .insert((slot.info.family.clone(), slot.info.variant));
+        }
+
+        slot.font.clone()
     }
 
+    /// The note's date if it has one. Otherwise, the current date at `offset`,
+    /// or at the space's UTC offset.
     fn today(&self, offset: Option<Duration>) -> Option<Datetime> {
-        let now = if let Some(duration) = offset {
+        if let Some(date) = self.date {
+            return Some(Datetime::Date(date));
+        }
+
+        let offset = match offset {
             #[allow(clippy::cast_possible_truncation)]
-            OffsetDateTime::now_utc()
-                .to_offset(UtcOffset::from_hms(duration.hours() as i8, 0, 0).unwrap())
-        } else {
-            OffsetDateTime::now_utc()
+            Some(duration) => UtcOffset::from_whole_seconds(duration.seconds() as i32).ok()?,
+            None => self
+                .space_id
+                .as_ref()
+                .and_then(|space_id| self.utc_offsets.get(space_id))
+                .copied()
+                .unwrap_or(UtcOffset::UTC),
         };
 
-        Some(Datetime::Date(now.date()))
+        let now = self.now.unwrap_or_else(OffsetDateTime::now_utc);
+
+        Some(Datetime::Date(now.to_offset(offset).date()))
     }
 }
 
+/// Builds the standard library with Mnemo's features and the given
+/// `sys.inputs`.
+fn build_library(inputs: Dict) -> Library {
+    let features = [Feature::Html, Feature::A11yExtras].into_iter().collect();
+
+    Library::builder()
+        .with_features(features)
+        .with_inputs(inputs)
+        .build()
+}
+
 impl IdeWorld for MnemoWorld {
     fn upcast(&self) -> &dyn World {
         self
@@ -189,3 +406,76 @@ impl FileSlot {
         }
     }
 }
+
+#[cfg(test)]
+fn pinned_world(space_offset_minutes: i32) -> MnemoWorld {
+    let mut world = MnemoWorld {
+        space_id: Some(String::from("space")),
+        ..MnemoWorld::default()
+    };
+    world.utc_offsets.insert(
+        String::from("space"),
+        UtcOffset::from_whole_seconds(space_offset_minutes * 60).unwrap(),
+    );
+
+    // 2024-03-05 20:00 UTC.
+    world.pin_now(Some(OffsetDateTime::from_unix_timestamp(1_709_668_800).unwrap()));
+
+    world
+}
+
+#[test]
+fn test_today_uses_minute_offsets() {
+    let date = |year, month, day| {
+        Some(Datetime::Date(
+            Date::from_calendar_date(year, time::Month::try_from(month).unwrap(), day).unwrap(),
+        ))
+    };
+
+    assert_eq!(pinned_world(0).today(None), date(2024, 3, 5));
+    assert_eq!(pinned_world(3 * 60 + 30).today(None), date(2024, 3, 5));
+    assert_eq!(pinned_world(5 * 60 + 30).today(None), date(2024, 3, 6));
+    assert_eq!(pinned_world(-(9 * 60 + 45)).today(None), date(2024, 3, 5));
+}
+
+#[test]
+fn test_today_prefers_note_date() {
+    let mut world = pinned_world(5 * 60 + 30);
+    let date = Date::from_calendar_date(2024, time::Month::February, 27).unwrap();
+    world.date = Some(date);
+
+    assert_eq!(world.today(None), Some(Datetime::Date(date)));
+}
+
+#[test]
+fn test_inputs_reach_only_their_note() {
+    use typst::foundations::{IntoValue, Str};
+    use typst_layout::PagedDocument;
+
+    let note = |path: &str| {
+        FileId::new(RootedPath::new(VirtualRoot::Project, VirtualPath::new(path).unwrap()))
+    };
+    let (a, b) = (note("a.typ"), note("b.typ"));
+
+    let mut world = MnemoWorld::default();
+    world.insert_source(a, String::from("#assert.eq(sys.inputs, (name: \"a\"))"));
+    world.insert_source(b, String::from("#assert.eq(sys.inputs, (:))"));
+
+    let mut inputs = Dict::new();
+    inputs.insert(Str::from("name"), "a".into_value());
+    world.set_inputs(a, inputs);
+
+    let compiles = |world: &mut MnemoWorld, id| {
+        world.synth_id = Some(id);
+        typst::compile::<PagedDocument>(world).output.is_ok()
+    };
+
+    assert!(compiles(&mut world, a));
+    assert!(compiles(&mut world, b));
+    assert!(std::ptr::eq(world.library(), &raw const world.library));
+
+    // Empty inputs drop the note's own library.
+    world.set_inputs(a, Dict::new());
+    assert!(!compiles(&mut world, a));
+    assert!(std::ptr::eq(world.library(), &raw const world.library));
+}